@@ -0,0 +1,344 @@
+use crate::{
+    bot::{find_clickpack_noise, read_players, ClickType, ClickpackFs, SoundLoader, Sounds},
+    dsp,
+    layout::{self, FolderMapping, FolderRules, MatchedBy},
+};
+use anyhow::Result;
+use kittyaudio::Frame;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Window after the onset that the loudness of a sound is measured in.
+const WINDOW_MS: f32 = 50.0;
+/// The onset is where the sound first gets this loud relative to its peak.
+const ONSET_DB: f32 = -20.0;
+const KMEANS_ITERATIONS: usize = 32;
+
+/// Sorting of clickpacks that are just a folder of recordings into categories,
+/// by how loud the sounds are and what their transient looks like.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct AutoSortSettings {
+    pub enabled: bool,
+    /// Number of loudness tiers, from 1 (only regular clicks) to 4 (hard,
+    /// regular, soft and micro clicks).
+    pub tiers: usize,
+    /// Tell releases apart from clicks.
+    pub releases: bool,
+}
+
+impl Default for AutoSortSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tiers: 4,
+            releases: true,
+        }
+    }
+}
+
+/// Loudness and transient shape of a sound.
+#[derive(Clone, Copy, Debug)]
+pub struct Features {
+    /// Peak level in dBFS.
+    pub peak: f32,
+    /// RMS level right after the onset in dBFS.
+    pub rms: f32,
+    /// Time from the onset to the peak in milliseconds.
+    pub attack: f32,
+}
+
+impl Features {
+    pub fn measure(frames: &[Frame], sample_rate: u32) -> Self {
+        let levels: Vec<f32> = frames
+            .iter()
+            .map(|f| f.left.abs().max(f.right.abs()))
+            .collect();
+        let (peak_at, peak) =
+            levels
+                .iter()
+                .copied()
+                .enumerate()
+                .fold(
+                    (0, 0.0),
+                    |max, (i, level)| if level > max.1 { (i, level) } else { max },
+                );
+        let threshold = peak * dsp::db_to_linear(ONSET_DB);
+        let onset = levels.iter().position(|&l| l >= threshold).unwrap_or(0);
+        let end = (onset + dsp::ms_to_frames(WINDOW_MS, sample_rate)).min(frames.len());
+        Self {
+            peak: dsp::peak_db(frames),
+            rms: dsp::rms_db(&frames[onset..end]),
+            attack: peak_at.saturating_sub(onset) as f32 * 1000.0 / sample_rate.max(1) as f32,
+        }
+    }
+}
+
+/// Cluster values into `k` groups, returns the group of every value. Group 0
+/// has the highest values.
+fn kmeans_1d(values: &[f32], k: usize) -> Vec<usize> {
+    if values.is_empty() {
+        return vec![];
+    }
+    let k = k.clamp(1, values.len());
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    // start at evenly spaced quantiles. clusters stay in order, so group 0
+    // keeps the highest values
+    let mut centers: Vec<f32> = (0..k)
+        .map(|i| sorted[(2 * i + 1) * sorted.len() / (2 * k)])
+        .collect();
+
+    let mut groups = vec![0; values.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        for (group, value) in groups.iter_mut().zip(values) {
+            *group = (0..k)
+                .min_by(|&a, &b| {
+                    (centers[a] - value)
+                        .abs()
+                        .total_cmp(&(centers[b] - value).abs())
+                })
+                .unwrap();
+        }
+        for (i, center) in centers.iter_mut().enumerate() {
+            let (sum, count) = groups
+                .iter()
+                .zip(values)
+                .filter(|(group, _)| **group == i)
+                .fold((0.0, 0), |(sum, count), (_, v)| (sum + v, count + 1));
+            if count > 0 {
+                *center = sum / count as f32;
+            }
+        }
+    }
+    groups
+}
+
+/// Values as standard scores, so features with different units can be added.
+fn standardize(values: impl Iterator<Item = f32> + Clone) -> Vec<f32> {
+    let n = values.clone().count().max(1) as f32;
+    let mean = values.clone().sum::<f32>() / n;
+    let var = values.clone().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+    let std = var.sqrt().max(f32::EPSILON);
+    values.map(|v| (v - mean) / std).collect()
+}
+
+/// How much each sound looks like a press rather than a release. Presses have
+/// a sharper transient than releases, loudness is left to the tiers.
+fn press_scores(features: &[Features]) -> Vec<f32> {
+    let crest = standardize(features.iter().map(|f| f.peak - f.rms));
+    let attack = standardize(features.iter().map(|f| f.attack));
+    crest.iter().zip(attack).map(|(c, a)| c - a).collect()
+}
+
+/// Click type of a loudness tier, tier 0 being the loudest.
+fn tier_type(tier: usize, tiers: usize, release: bool) -> ClickType {
+    use ClickType::*;
+    let types: &[(ClickType, ClickType)] = match tiers {
+        0 | 1 => &[(Click, Release)],
+        2 => &[(Click, Release), (SoftClick, SoftRelease)],
+        3 => &[
+            (HardClick, HardRelease),
+            (Click, Release),
+            (SoftClick, SoftRelease),
+        ],
+        _ => &[
+            (HardClick, HardRelease),
+            (Click, Release),
+            (SoftClick, SoftRelease),
+            (MicroClick, MicroRelease),
+        ],
+    };
+    let (click, rel) = types[tier.min(types.len() - 1)];
+    if release {
+        rel
+    } else {
+        click
+    }
+}
+
+/// Click types for sounds with the given features.
+pub fn categorize(features: &[Features], settings: &AutoSortSettings) -> Vec<ClickType> {
+    let is_release: Vec<bool> = if settings.releases && features.len() >= 2 {
+        kmeans_1d(&press_scores(features), 2)
+            .into_iter()
+            .map(|group| group == 1)
+            .collect()
+    } else {
+        vec![false; features.len()]
+    };
+
+    let mut types = vec![ClickType::Click; features.len()];
+    for release in [false, true] {
+        let group: Vec<usize> = (0..features.len())
+            .filter(|&i| is_release[i] == release)
+            .collect();
+        let rms: Vec<f32> = group.iter().map(|&i| features[i].rms).collect();
+        for (i, tier) in group.into_iter().zip(kmeans_1d(&rms, settings.tiers)) {
+            types[i] = tier_type(tier, settings.tiers, release);
+        }
+    }
+    types
+}
+
+/// Move every sound into the category that fits it best.
+pub fn sort_sounds(sounds: &mut Sounds, settings: &AutoSortSettings) {
+    let mut all = vec![];
+    for (_, clicks) in sounds.categories_mut() {
+        all.append(clicks);
+    }
+    let features: Vec<Features> = all
+        .iter()
+        .map(|sound| Features::measure(&sound.frames, sound.sample_rate()))
+        .collect();
+    for (sound, typ) in all.into_iter().zip(categorize(&features, settings)) {
+        if let Some(clicks) = sounds.sounds_mut(typ) {
+            clicks.push(sound);
+        }
+    }
+}
+
+/// Sort the sounds of players that are only loaded from files that couldn't
+/// be categorized otherwise, and update the folders they were loaded from.
+pub fn sort_unsorted(
+    players: &mut (Sounds, Sounds),
+    folders: &mut Vec<FolderMapping>,
+    settings: &AutoSortSettings,
+) {
+    for player2 in [false, true] {
+        let mut player_folders = folders.iter().filter(|f| f.player2 == player2).peekable();
+        let is_unsorted = player_folders.peek().is_some()
+            && player_folders.all(|f| f.matched_by == MatchedBy::Nothing);
+        if !is_unsorted {
+            continue;
+        }
+        let sounds = if player2 {
+            &mut players.1
+        } else {
+            &mut players.0
+        };
+        log::info!("sorting player {} sounds by loudness", player2 as u8 + 1);
+        sort_sounds(sounds, settings);
+
+        folders.retain(|f| f.player2 != player2);
+        for (typ, clicks) in sounds.categories() {
+            for click in clicks {
+                let dir = click.path.parent().unwrap_or(Path::new("")).to_path_buf();
+                let folder = folders
+                    .iter_mut()
+                    .find(|f| f.player2 == player2 && f.path == dir && f.typ == typ);
+                match folder {
+                    Some(folder) => folder.files.push(click.path.clone()),
+                    None => folders.push(FolderMapping {
+                        path: dir,
+                        player2,
+                        button: None,
+                        typ,
+                        matched_by: MatchedBy::Loudness,
+                        files: vec![click.path.clone()],
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// Path in `dir` that doesn't exist yet, numbering the file if it does.
+fn unique_path(dir: &Path, name: &Path) -> PathBuf {
+    let mut path = dir.join(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = name.extension().map(|e| e.to_string_lossy());
+    let mut i = 2;
+    while path.exists() {
+        let numbered = match &ext {
+            Some(ext) => format!("{stem} ({i}).{ext}"),
+            None => format!("{stem} ({i})"),
+        };
+        path = dir.join(numbered);
+        i += 1;
+    }
+    path
+}
+
+/// Sort a clickpack and copy its files into `out` with the category folder
+/// layout. Player 1 sounds go in the root, player 2 sounds in `player2`.
+/// Returns the number of sounds that were written.
+pub fn export_sorted(
+    path: &Path,
+    out: &Path,
+    rules: &FolderRules,
+    settings: &AutoSortSettings,
+) -> Result<usize> {
+    anyhow::ensure!(!out.exists(), "{} already exists", out.display());
+    let (fs, root) = ClickpackFs::open(path, rules)?;
+    let mut folders = layout::map_folders(&fs, &root, rules);
+    // no fmod system, only the paths and frames are needed
+    let loader = SoundLoader::new(std::ptr::null_mut());
+    let mut players = read_players(&fs, &folders, &loader);
+    anyhow::ensure!(
+        players.0.has_sounds() || players.1.has_sounds(),
+        "no sounds found in clickpack"
+    );
+    sort_unsorted(&mut players, &mut folders, settings);
+
+    let mut written = 0;
+    for (player_dir, sounds) in [
+        (out.to_path_buf(), &players.0),
+        (out.join("player2"), &players.1),
+    ] {
+        for (typ, clicks) in sounds.categories() {
+            for click in clicks {
+                let mut dir = player_dir.clone();
+                if let Some(button) = click.button {
+                    dir.push(button.dirname());
+                }
+                dir.push(typ.dirname());
+                std::fs::create_dir_all(&dir)?;
+                let name = click.path.file_name().unwrap_or_default();
+                std::fs::write(unique_path(&dir, Path::new(name)), fs.read(&click.path)?)?;
+                written += 1;
+            }
+        }
+    }
+    if let Some(noise) = find_clickpack_noise(&fs, &root, rules) {
+        let name = noise.file_name().unwrap_or_default();
+        std::fs::write(out.join(name), fs.read(&noise)?)?;
+    }
+    Ok(written)
+}
+
+#[test]
+fn test_categorize() {
+    // a hard, regular, soft and micro click, each a bit louder than the release
+    let click = |level: f32, attack: usize| -> Vec<Frame> {
+        (0..2400)
+            .map(|i| {
+                let env = if i < attack {
+                    i as f32 / attack as f32
+                } else {
+                    (-((i - attack) as f32) / 200.0).exp()
+                };
+                let x = level * env * if i % 2 == 0 { 1.0 } else { -1.0 };
+                Frame { left: x, right: x }
+            })
+            .collect()
+    };
+    let mut features = vec![];
+    for level in [1.0, 0.3, 0.1, 0.03] {
+        features.push(Features::measure(&click(level, 5), 48_000));
+        features.push(Features::measure(&click(level * 0.7, 200), 48_000));
+    }
+    use ClickType::*;
+    assert_eq!(
+        categorize(&features, &AutoSortSettings::default()),
+        [
+            HardClick,
+            HardRelease,
+            Click,
+            Release,
+            SoftClick,
+            SoftRelease,
+            MicroClick,
+            MicroRelease
+        ]
+    );
+}
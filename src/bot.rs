@@ -1,6 +1,21 @@
 use crate::{
-    hooks,
+    autosort::{self, AutoSortSettings},
+    builder::{self, BuilderSettings},
+    cache::{CacheSettings, PcmCache},
+    dsp, hooks,
+    layout::{self, FolderMapping, FolderRules, MatchedBy},
+    lint::{self, ClickpackReport, Severity},
+    loudness::{
+        self, LoudnessCache, NormalizationMethod, NormalizationScope, NormalizationSettings,
+    },
+    noise::{
+        self, ActivityEnvelope, NoiseColor, NoiseGeneratorSettings, NoiseLayer, NoiseLoopSettings,
+        NoiseModulationMode, NoiseModulationSettings, NOISE_LIBRARY_DIR,
+    },
+    selection::{LayerMode, SelectionMode, Selector},
+    simulator::{Pattern, SimulatedAction, SimulatorSettings},
     utils::{self, IntoFmodResult},
+    watcher::ClickpackWatcher,
 };
 use anyhow::Result;
 use egui::{
@@ -24,16 +39,22 @@ use geometrydash::{
     },
     AddressUtils, FMODAudioEngine, PlayLayer, PlayerObject,
 };
-use kittyaudio::{Device, Mixer, PlaybackRate, Sound, SoundHandle, StreamSettings};
+use kittyaudio::{Device, Frame, Mixer, PlaybackRate, Sound, SoundHandle, StreamSettings};
 use once_cell::sync::Lazy;
-use rand::{prelude::SliceRandom, Rng};
+use rand::{seq::SliceRandom, Rng};
+use rayon::prelude::*;
 use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, VecDeque},
+    io::{Cursor, Read},
     ops::{Deref, DerefMut, RangeInclusive},
     path::{Path, PathBuf},
     process::Command,
-    sync::{Arc, Mutex, Once},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, Once, TryLockError,
+    },
     time::{Duration, Instant},
 };
 use windows::Win32::System::Console::{AllocConsole, FreeConsole};
@@ -41,6 +62,13 @@ use windows::Win32::System::Console::{AllocConsole, FreeConsole};
 /// Global bot state
 pub static mut BOT: Lazy<Box<Bot>> = Lazy::new(Box::<Bot>::default);
 
+/// Held while a clickpack is being loaded, so only one load runs at a time.
+static LOAD_LOCK: Mutex<()> = Mutex::new(());
+/// Held while noise library files are loaded.
+static NOISE_LOCK: Mutex<()> = Mutex::new(());
+/// Held while clickpack layers are loaded.
+static LAYER_LOCK: Mutex<()> = Mutex::new(());
+
 pub mod built_info {
     // generated by build.rs
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
@@ -82,6 +110,24 @@ impl Default for Pitch {
     }
 }
 
+impl Pitch {
+    /// Replace values that would break picking a random pitch, so the range
+    /// is finite, positive and not inverted.
+    pub fn validate(&mut self) {
+        let default = Self::default();
+        for (value, default) in [
+            (&mut self.from, default.from),
+            (&mut self.to, default.to),
+            (&mut self.step, default.step),
+        ] {
+            if !value.is_finite() || *value < 0.0 {
+                *value = default;
+            }
+        }
+        self.to = self.to.max(self.from);
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct VolumeSettings {
     pub enabled: bool,
@@ -107,7 +153,188 @@ impl Default for VolumeSettings {
     }
 }
 
-#[derive(Default, Clone, Copy, Debug, PartialEq)]
+impl VolumeSettings {
+    /// Replace values that would break computing the volume of a click.
+    pub fn validate(&mut self) {
+        let default = Self::default();
+        for (value, default) in [
+            (&mut self.spam_time, default.spam_time),
+            (
+                &mut self.spam_vol_offset_factor,
+                default.spam_vol_offset_factor,
+            ),
+            (&mut self.max_spam_vol_offset, default.max_spam_vol_offset),
+            (&mut self.global_volume, default.global_volume),
+            (&mut self.volume_var, default.volume_var),
+        ] {
+            if !value.is_finite() {
+                *value = default;
+            }
+        }
+        // the random volume offset is picked from `-volume_var..=volume_var`
+        self.volume_var = self.volume_var.abs();
+        self.global_volume = self.global_volume.max(0.0);
+    }
+}
+
+/// Generates a struct where every field of `$target` is optional, which is used
+/// to partially override settings (e.g. from a clickpack manifest).
+macro_rules! make_override {
+    ($name:ident => $target:ty { $($field:ident: $typ:ty),+ $(,)? }) => {
+        #[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+        #[serde(default)]
+        pub struct $name {
+            $(
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $field: Option<$typ>,
+            )+
+        }
+
+        impl $name {
+            pub fn apply(&self, target: &mut $target) {
+                $(
+                    if let Some(value) = self.$field.clone() {
+                        target.$field = value;
+                    }
+                )+
+            }
+
+            pub fn is_empty(&self) -> bool {
+                $(self.$field.is_none())&&+
+            }
+        }
+    };
+}
+
+make_override!(TimingsOverride => Timings {
+    hard: f32,
+    regular: f32,
+    soft: f32,
+});
+
+make_override!(PitchOverride => Pitch {
+    from: f64,
+    to: f64,
+    step: f64,
+});
+
+make_override!(VolumeSettingsOverride => VolumeSettings {
+    enabled: bool,
+    spam_time: f32,
+    spam_vol_offset_factor: f32,
+    max_spam_vol_offset: f32,
+    change_releases_volume: bool,
+    global_volume: f32,
+    volume_var: f32,
+});
+
+/// Name of the optional manifest file in the root of a clickpack.
+pub const MANIFEST_FILENAME: &str = "clickpack.json";
+
+/// Optional `clickpack.json` file that describes a clickpack.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ClickpackManifest {
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    /// Overrides the user's timings while this clickpack is loaded.
+    pub timings: TimingsOverride,
+    /// Overrides the user's pitch settings while this clickpack is loaded.
+    pub pitch: PitchOverride,
+    /// Overrides the user's volume settings while this clickpack is loaded.
+    pub volume_settings: VolumeSettingsOverride,
+    /// Gain for each category folder, e.g. `"softclicks": 0.8`.
+    pub category_gains: HashMap<String, f32>,
+    /// Gain for single files, either a path relative to the clickpack root
+    /// (`"player1/clicks/3.wav"`) or just a file name (`"3.wav"`).
+    pub file_gains: HashMap<String, f32>,
+    /// Selection weights for single files, keyed the same way as `file_gains`.
+    /// Overrides weights set with a filename suffix.
+    pub file_weights: HashMap<String, f32>,
+}
+
+impl ClickpackManifest {
+    /// Read the manifest in the root of a clickpack. Returns [None] if there is
+    /// no manifest.
+    pub fn read(fs: &ClickpackFs, root: &Path) -> Result<Option<Self>> {
+        let Ok(data) = fs.read(&root.join(MANIFEST_FILENAME)) else {
+            return Ok(None);
+        };
+        let manifest = serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("failed to parse {MANIFEST_FILENAME}: {e}"))?;
+        log::info!("loaded {MANIFEST_FILENAME}");
+        Ok(Some(manifest))
+    }
+
+    /// Apply the pitch override on top of `pitch`. If it only sets one end of
+    /// the range and that ends up past the other end, the other end is moved
+    /// along with it.
+    pub fn apply_pitch(&self, pitch: &mut Pitch) {
+        self.pitch.apply(pitch);
+        if pitch.from > pitch.to {
+            if self.pitch.from.is_some() {
+                pitch.to = pitch.from;
+            } else {
+                pitch.from = pitch.to;
+            }
+        }
+        pitch.validate();
+    }
+
+    /// Apply the volume settings override on top of `vol`.
+    pub fn apply_volume_settings(&self, vol: &mut VolumeSettings) {
+        self.volume_settings.apply(vol);
+        vol.validate();
+    }
+
+    #[inline]
+    pub fn has_overrides(&self) -> bool {
+        !self.timings.is_empty() || !self.pitch.is_empty() || !self.volume_settings.is_empty()
+    }
+
+    /// Look up a per-file value, first by the path relative to `root` and then
+    /// by the file name.
+    fn file_value(map: &HashMap<String, f32>, root: &Path, path: &Path) -> Option<f32> {
+        if map.is_empty() {
+            return None;
+        }
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let filename = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        map.get(&relative).or_else(|| map.get(&filename)).copied()
+    }
+
+    /// Gain multiplier for a sound at `path` in the `typ` category.
+    pub fn gain_for(&self, root: &Path, path: &Path, typ: ClickType) -> f32 {
+        let gain = self
+            .category_gains
+            .get(typ.dirname())
+            .copied()
+            .unwrap_or(1.0);
+        gain * Self::file_value(&self.file_gains, root, path).unwrap_or(1.0)
+    }
+
+    /// Apply the per-category/per-file gains and the per-file weights to every sound.
+    pub fn apply_to_sounds(&self, root: &Path, sounds: &mut Sounds) {
+        for (typ, sounds) in sounds.categories_mut() {
+            for sound in sounds {
+                sound.gain = self.gain_for(root, &sound.path, typ);
+                if let Some(weight) = Self::file_value(&self.file_weights, root, &sound.path) {
+                    sound.weight = weight.max(0.0);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ClickType {
     HardClick,
     HardRelease,
@@ -235,6 +462,21 @@ impl ClickType {
         }
     }
 
+    /// Name of the folder this click type is loaded from.
+    pub const fn dirname(self) -> &'static str {
+        match self {
+            ClickType::HardClick => "hardclicks",
+            ClickType::HardRelease => "hardreleases",
+            ClickType::Click => "clicks",
+            ClickType::Release => "releases",
+            ClickType::SoftClick => "softclicks",
+            ClickType::SoftRelease => "softreleases",
+            ClickType::MicroClick => "microclicks",
+            ClickType::MicroRelease => "microreleases",
+            ClickType::None => "",
+        }
+    }
+
     #[inline]
     pub const fn is_release(self) -> bool {
         matches!(
@@ -247,24 +489,289 @@ impl ClickType {
     }
 }
 
+/// Button that caused an action. Clickpacks can have a folder with categories
+/// for every button, buttons without sounds use the normal categories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Jump,
+    Left,
+    Right,
+}
+
+impl Button {
+    pub const ALL: [Self; 3] = [Self::Jump, Self::Left, Self::Right];
+
+    /// Button from the id the game passes to `pushButton`/`releaseButton`.
+    /// Unknown ids are treated as jumps.
+    pub const fn from_id(id: i32) -> Self {
+        match id {
+            2 => Self::Left,
+            3 => Self::Right,
+            _ => Self::Jump,
+        }
+    }
+
+    pub const fn dirname(self) -> &'static str {
+        match self {
+            Self::Jump => "jump",
+            Self::Left => "left",
+            Self::Right => "right",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProcessingSettings {
+    pub enabled: bool,
+    pub trim_silence: bool,
+    /// Anything quieter than this (in dB) at the start of a sound is cut off.
+    pub silence_threshold: f32,
+    /// Length of the fade-in in milliseconds.
+    pub fade_in: f32,
+    /// Length of the fade-out in milliseconds.
+    pub fade_out: f32,
+}
+
+impl Default for ProcessingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            trim_silence: true,
+            silence_threshold: -50.0,
+            fade_in: 0.5,
+            fade_out: 5.0,
+        }
+    }
+}
+
+impl ProcessingSettings {
+    /// Frames kept before the first audible frame, so the transient isn't cut.
+    const TRIM_MARGIN_MS: f32 = 0.5;
+
+    /// Trim leading silence and apply fades. Returns [None] if nothing was changed.
+    pub fn process(&self, sound: &Sound) -> Option<Sound> {
+        if !self.enabled || sound.frames.is_empty() {
+            return None;
+        }
+        let sample_rate = sound.sample_rate();
+        let mut start = 0;
+        if self.trim_silence {
+            // don't touch fully silent sounds
+            if let Some(first) =
+                dsp::first_audible_frame(&sound.frames, dsp::db_to_linear(self.silence_threshold))
+            {
+                start = first.saturating_sub(dsp::ms_to_frames(Self::TRIM_MARGIN_MS, sample_rate));
+            }
+        }
+        let fade_in = dsp::ms_to_frames(self.fade_in, sample_rate);
+        let fade_out = dsp::ms_to_frames(self.fade_out, sample_rate);
+        if start == 0 && fade_in == 0 && fade_out == 0 {
+            return None;
+        }
+
+        let mut frames: Vec<Frame> = sound.frames[start..].to_vec();
+        dsp::fade_in(&mut frames, fade_in);
+        dsp::fade_out(&mut frames, fade_out);
+        log::trace!("trimmed {start} frames of leading silence");
+        Some(Sound::from_frames(sample_rate, &frames))
+    }
+
+    /// Changes whenever processing changes the decoded audio, used in cache keys.
+    pub fn cache_salt(&self) -> String {
+        if self.enabled {
+            serde_json::to_string(self).unwrap_or_default()
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Decodes sounds for the kittyaudio and FMOD backends.
+#[derive(Clone)]
+pub struct SoundLoader {
+    pub system: *mut FMOD_SYSTEM,
+    /// Load-time processing, [None] to load sounds as-is.
+    pub processing: Option<ProcessingSettings>,
+    /// Cache for decoded sounds, [None] to always decode.
+    pub cache: Option<PcmCache>,
+    /// Progress of the clickpack that is being loaded.
+    pub progress: Option<Arc<LoadProgress>>,
+    /// Decoded frames of the previous load of the clickpack, by
+    /// [SoundWrapper::source].
+    pub reuse: Option<Arc<ReusableSounds>>,
+    /// Resample sounds to [dsp::CANONICAL_SAMPLE_RATE] when creating FMOD
+    /// sounds. Otherwise FMOD resamples them while playing, which is cheaper
+    /// if FMOD isn't used for playback.
+    pub resample: bool,
+}
+
+pub type ReusableSounds = HashMap<String, (u32, Arc<[Frame]>)>;
+
+impl SoundLoader {
+    pub fn new(system: *mut FMOD_SYSTEM) -> Self {
+        Self {
+            system,
+            processing: None,
+            cache: None,
+            progress: None,
+            reuse: None,
+            resample: false,
+        }
+    }
+
+    pub fn with_processing(mut self, processing: ProcessingSettings) -> Self {
+        self.processing = Some(processing);
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Option<PcmCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: Arc<LoadProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_reuse(mut self, reuse: ReusableSounds) -> Self {
+        self.reuse = Some(Arc::new(reuse));
+        self
+    }
+
+    pub fn with_resampling(mut self, resample: bool) -> Self {
+        self.resample = resample;
+        self
+    }
+
+    fn cache_salt(&self) -> String {
+        self.processing
+            .as_ref()
+            .map(ProcessingSettings::cache_salt)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn is_cancelled(&self) -> bool {
+        self.progress
+            .as_deref()
+            .is_some_and(LoadProgress::is_cancelled)
+    }
+}
+
+/// Progress of a clickpack that is being loaded, shared between the loading
+/// threads and the UI.
+#[derive(Default)]
+pub struct LoadProgress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    /// File that was started most recently.
+    current: Mutex<PathBuf>,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    /// Stop loading as soon as possible. Files that are being decoded right now
+    /// are still finished.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Number of decoded files and the total number of files.
+    pub fn files(&self) -> (usize, usize) {
+        let total = self.total.load(Ordering::Relaxed);
+        // the total is a guess, more files are decoded if a clickpack has no
+        // category folders
+        (self.done.load(Ordering::Relaxed).min(total), total)
+    }
+
+    pub fn current_file(&self) -> PathBuf {
+        self.current.lock().unwrap().clone()
+    }
+
+    fn add_files(&self, count: usize) {
+        self.total.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn start_file(&self, path: &Path) {
+        *self.current.lock().unwrap() = path.to_path_buf();
+    }
+
+    fn finish_file(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Clone)]
 pub struct SoundWrapper {
     sound: Sound,
     fmod_sound: *mut FMOD_SOUND,
+    /// Path this sound was loaded from. For zipped clickpacks this is
+    /// relative to the archive.
+    pub path: PathBuf,
+    /// Volume multiplier set by the clickpack manifest.
+    pub gain: f32,
+    /// Relative probability of this sound being picked inside of its category.
+    /// A weight of 0 never plays the sound.
+    pub weight: f32,
+    /// Identifies the file and the settings it was decoded with, so the sound can
+    /// be reused if the clickpack is reloaded and the file didn't change.
+    pub source: Option<String>,
+    /// Button bank this sound belongs to, [None] for the normal categories.
+    pub button: Option<Button>,
 }
 
 impl SoundWrapper {
-    pub fn from_path(system: *mut FMOD_SYSTEM, path: &Path) -> Result<Self> {
-        // load kittyaudio sound
-        let sound = Sound::from_path(path)?;
+    /// Wrap a decoded sound, see [ClickpackFs::decode].
+    pub(crate) fn from_decoded(loader: &SoundLoader, sound: Sound, path: &Path) -> Self {
+        // sounds loaded without an fmod system (e.g. for analysis) only use kittyaudio
+        let fmod_sound = if loader.system.is_null() {
+            std::ptr::null_mut()
+        } else {
+            Self::create_fmod_sound(loader.system, &sound, loader.resample)
+        };
+
+        Self {
+            sound,
+            fmod_sound,
+            path: path.to_path_buf(),
+            gain: 1.0,
+            weight: weight_from_filename(path).unwrap_or(1.0),
+            source: None,
+            button: None,
+        }
+    }
+
+    /// Interleaved stereo samples for fmod and their sample rate, either at the
+    /// rate of its mixer or at the rate of the sound.
+    fn fmod_samples(sound: &Sound, resample: bool) -> (Vec<f32>, u32) {
+        if resample {
+            let samples = dsp::frames_to_canonical(&sound.frames, sound.sample_rate());
+            (samples, dsp::CANONICAL_SAMPLE_RATE)
+        } else {
+            (dsp::interleave(&sound.frames), sound.sample_rate())
+        }
+    }
+
+    fn create_fmod_sound(
+        system: *mut FMOD_SYSTEM,
+        sound: &Sound,
+        resample: bool,
+    ) -> *mut FMOD_SOUND {
+        let (samples, sample_rate) = Self::fmod_samples(sound, resample);
 
         // create fmod sound exinfo, we want to load the sound from memory
         let mut exinfo: FMOD_CREATESOUNDEXINFO = unsafe { std::mem::zeroed() };
         exinfo.cbsize = std::mem::size_of::<FMOD_CREATESOUNDEXINFO>() as i32;
         exinfo.numchannels = 2;
         exinfo.format = FMOD_SOUND_FORMAT_PCMFLOAT;
-        exinfo.defaultfrequency = sound.sample_rate() as i32;
-        exinfo.length = sound.frames.len() as u32 * std::mem::size_of::<f32>() as u32 * 2;
+        exinfo.defaultfrequency = sample_rate as i32;
+        exinfo.length = (samples.len() * std::mem::size_of::<f32>()) as u32;
 
         // create fmod sound
         let mut fmod_sound: *mut FMOD_SOUND = std::ptr::null_mut();
@@ -272,7 +779,7 @@ impl SoundWrapper {
             // we ignore this error because it doesn't matter if you use the kittyaudio backend
             let _ = FMOD_System_CreateSound(
                 system,
-                sound.frames.as_ptr() as *const i8,
+                samples.as_ptr() as *const i8,
                 FMOD_OPENMEMORY | FMOD_OPENRAW | FMOD_LOOP_OFF,
                 &mut exinfo,
                 &mut fmod_sound,
@@ -280,11 +787,13 @@ impl SoundWrapper {
             .fmod_result()
             .map_err(|e| log::error!("failed to create fmod sound: {e}"));
         };
-
-        Ok(Self { sound, fmod_sound })
+        fmod_sound
     }
 
     fn free(&mut self) {
+        if self.fmod_sound.is_null() {
+            return;
+        }
         let _ = unsafe {
             FMOD_Sound_Release(self.fmod_sound)
                 .fmod_result()
@@ -318,103 +827,405 @@ pub struct Sounds {
     pub softreleases: Vec<SoundWrapper>,
     pub microclicks: Vec<SoundWrapper>,
     pub microreleases: Vec<SoundWrapper>,
+    /// Files that couldn't be decoded, along with the error.
+    pub failed: Vec<(PathBuf, String)>,
+    /// Sample selection state for each category, indexed by [ClickType]. The
+    /// normal categories come first, then the ones of every [Button].
+    selectors: [[Selector; 8]; 4],
+}
+
+/// Extensions of the files that sounds are decoded from.
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "wav", "wave", "mp3", "ogg", "oga", "flac", "m4a", "aac", "aif", "aiff", "caf",
+];
+
+/// Largest file that is read from a zip archive, in bytes.
+const MAX_ZIP_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+#[inline]
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        AUDIO_EXTENSIONS
+            .iter()
+            .any(|audio| ext.eq_ignore_ascii_case(audio))
+    })
+}
+
+#[inline]
+pub fn is_zip_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Where the files of a clickpack are read from.
+pub enum ClickpackFs {
+    /// A regular directory on disk.
+    Dir,
+    /// A zip archive decompressed into memory. Paths are relative to the archive root.
+    Zip {
+        /// Identifies the archive, see [file_identity].
+        id: String,
+        files: HashMap<PathBuf, Arc<[u8]>>,
+    },
+}
+
+/// Identifies a file on disk by its path, size and modification time.
+/// Used for cache keys.
+pub fn file_identity(path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(path).ok()?;
+    let modified = meta
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some(format!("{}|{}|{modified}", path.display(), meta.len()))
+}
+
+impl ClickpackFs {
+    /// Open a clickpack folder or `.zip` archive. Returns the filesystem and the
+    /// clickpack root inside of it.
+    pub fn open(path: &Path, rules: &FolderRules) -> Result<(Self, PathBuf)> {
+        if !is_zip_file(path) {
+            return Ok((Self::Dir, path.to_path_buf()));
+        }
+
+        log::info!("reading zip archive {path:?}");
+        let f = std::fs::File::open(path)?;
+        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(f))?;
+        let mut files = HashMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let Some(name) = file.enclosed_name().map(Path::to_path_buf) else {
+                log::warn!("skipping invalid path in archive: \"{}\"", file.name());
+                continue;
+            };
+            // junk that macOS puts in archives
+            if name.starts_with("__MACOSX") {
+                continue;
+            }
+            let is_manifest = name.file_name().is_some_and(|f| f == MANIFEST_FILENAME);
+            if !is_audio_file(&name) && !is_manifest {
+                log::debug!("skipping {name:?} in archive, not an audio file");
+                continue;
+            }
+            // the size in the header can't be trusted, so don't preallocate it
+            let mut data = vec![];
+            file.by_ref()
+                .take(MAX_ZIP_ENTRY_SIZE + 1)
+                .read_to_end(&mut data)?;
+            if data.len() as u64 > MAX_ZIP_ENTRY_SIZE {
+                log::warn!("skipping {name:?} in archive, larger than {MAX_ZIP_ENTRY_SIZE} bytes");
+                continue;
+            }
+            files.insert(name, data.into());
+        }
+
+        let fs = Self::Zip {
+            id: file_identity(path).unwrap_or_else(|| path.display().to_string()),
+            files,
+        };
+        let root = fs.find_root(rules);
+        log::debug!("zip clickpack root: {root:?}");
+        Ok((fs, root))
+    }
+
+    /// Archives usually contain the clickpack inside of one or more nested folders,
+    /// so descend into single subfolders until we find something that looks like
+    /// the root of a clickpack.
+    fn find_root(&self, rules: &FolderRules) -> PathBuf {
+        let mut root = PathBuf::new();
+        loop {
+            if !self.files_in(&root).is_empty() {
+                break;
+            }
+            let dirs = self.dirs_in(&root);
+            let [dir] = dirs.as_slice() else {
+                break;
+            };
+            let name = dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if rules.is_known(&name) {
+                break;
+            }
+            root = dir.clone();
+        }
+        root
+    }
+
+    /// Files directly inside of `dir`, sorted by path.
+    pub fn files_in(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = match self {
+            Self::Dir => {
+                let Ok(entries) = dir.read_dir() else {
+                    return vec![];
+                };
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|path| path.is_file())
+                    .collect()
+            }
+            Self::Zip { files, .. } => files
+                .keys()
+                .filter(|path| path.parent() == Some(dir))
+                .cloned()
+                .collect(),
+        };
+        files.sort();
+        files
+    }
+
+    /// Folders directly inside of `dir`, sorted by path.
+    pub fn dirs_in(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = match self {
+            Self::Dir => {
+                let Ok(entries) = dir.read_dir() else {
+                    return vec![];
+                };
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|path| path.is_dir())
+                    .collect()
+            }
+            Self::Zip { files, .. } => files
+                .keys()
+                .filter_map(|path| {
+                    let mut components = path.strip_prefix(dir).ok()?.components();
+                    let first = components.next()?;
+                    // only count it if it's not the file itself
+                    components.next()?;
+                    Some(dir.join(first))
+                })
+                .collect(),
+        };
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    pub fn load_sound(&self, loader: &SoundLoader, path: &Path) -> Result<SoundWrapper> {
+        let sound = self.decode(loader.processing.as_ref(), loader.cache.as_ref(), path)?;
+        Ok(SoundWrapper::from_decoded(loader, sound, path))
+    }
+
+    /// Decode and process a sound, reading it from the cache if possible. This
+    /// doesn't touch FMOD, so it can run on any thread.
+    pub fn decode(
+        &self,
+        processing: Option<&ProcessingSettings>,
+        cache: Option<&PcmCache>,
+        path: &Path,
+    ) -> Result<Sound> {
+        // the file is read once, to hash it and to decode it
+        let data: Arc<[u8]> = match self {
+            Self::Dir => std::fs::read(path)?.into(),
+            Self::Zip { files, .. } => match files.get(path) {
+                Some(data) => data.clone(),
+                None => anyhow::bail!("{path:?} not found in archive"),
+            },
+        };
+
+        // the contents are hashed so the cache can't return a stale sound
+        let key = cache.and_then(|_| {
+            let salt = processing
+                .map(ProcessingSettings::cache_salt)
+                .unwrap_or_default();
+            Some(PcmCache::key(&self.file_key(path)?, &data, &salt))
+        });
+        if let Some(sound) = cache.zip(key.as_ref()).and_then(|(c, key)| c.get(key)) {
+            log::trace!("loaded {path:?} from cache");
+            return Ok(sound);
+        }
+
+        let mut sound = Sound::from_cursor(Cursor::new(data))?;
+        // trim silence, apply fades. the fmod sound is created from the same
+        // frames, so this applies to both backends
+        if let Some(processed) = processing.and_then(|p| p.process(&sound)) {
+            sound = processed;
+        }
+        if let Some((cache, key)) = cache.zip(key.as_ref()) {
+            cache.insert(key, &sound);
+        }
+        Ok(sound)
+    }
+
+    /// Identifies a file of the clickpack, see [file_identity].
+    pub fn file_key(&self, path: &Path) -> Option<String> {
+        match self {
+            Self::Dir => file_identity(path),
+            Self::Zip { id, files } => Some(format!(
+                "{id}!{}|{}",
+                path.display(),
+                files.get(path)?.len()
+            )),
+        }
+    }
+
+    /// Read the whole contents of a file.
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        match self {
+            Self::Dir => Ok(std::fs::read(path)?),
+            Self::Zip { files, .. } => match files.get(path) {
+                Some(data) => Ok(data.to_vec()),
+                None => anyhow::bail!("{path:?} not found in archive"),
+            },
+        }
+    }
+}
+
+/// Parse a selection weight from a filename suffix, for example `click1@0.5.wav`
+/// has a weight of 0.5.
+fn weight_from_filename(path: &Path) -> Option<f32> {
+    let stem = path.file_stem()?.to_str()?;
+    let (_, weight) = stem.rsplit_once('@')?;
+    let weight: f32 = weight.trim().parse().ok()?;
+    weight.is_finite().then_some(weight.max(0.0))
 }
 
-fn read_clicks_in_directory(dir: &Path, system: *mut FMOD_SYSTEM) -> Vec<SoundWrapper> {
-    let Ok(dir) = dir.read_dir() else {
-        // log::warn!("can't find directory {dir:?}, skipping");
+fn read_clicks(
+    fs: &ClickpackFs,
+    files: &[PathBuf],
+    loader: &SoundLoader,
+    failed: &mut Vec<(PathBuf, String)>,
+) -> Vec<SoundWrapper> {
+    // decode on all cores. fmod sounds are created on this thread afterwards,
+    // the loader isn't thread safe
+    let (processing, cache) = (loader.processing.as_ref(), loader.cache.as_ref());
+    let (progress, reuse) = (loader.progress.as_deref(), loader.reuse.as_deref());
+    let salt = loader.cache_salt();
+    let decoded: Vec<(PathBuf, Option<String>, Option<Result<Sound>>)> = files
+        .par_iter()
+        .cloned()
+        .map(|path| {
+            if progress.is_some_and(LoadProgress::is_cancelled) {
+                return (path, None, None);
+            }
+            if let Some(progress) = progress {
+                progress.start_file(&path);
+            }
+            // files that didn't change since the last load aren't decoded again
+            let source = fs.file_key(&path).map(|key| format!("{key}|{salt}"));
+            let reused = source
+                .as_ref()
+                .and_then(|source| reuse?.get(source))
+                .map(|(sample_rate, frames)| Ok(Sound::from_frames(*sample_rate, frames)));
+            let sound = reused.unwrap_or_else(|| fs.decode(processing, cache, &path));
+            if let Some(progress) = progress {
+                progress.finish_file();
+            }
+            (path, source, Some(sound))
+        })
+        .collect();
+    if loader.is_cancelled() {
         return vec![];
-    };
+    }
+
     let mut sounds = vec![];
-    for entry in dir {
-        let path = entry.unwrap().path();
-        if path.is_file() {
-            let sound = SoundWrapper::from_path(system, &path);
-            if let Ok(sound) = sound {
+    for (path, source, sound) in decoded {
+        match sound {
+            Some(Ok(sound)) => {
+                let mut sound = SoundWrapper::from_decoded(loader, sound, &path);
+                sound.source = source;
                 sounds.push(sound);
-            } else if let Err(e) = sound {
+            }
+            Some(Err(e)) => {
                 log::error!("failed to load '{path:?}': {e}");
+                failed.push((path, e.to_string()));
             }
+            None => {}
         }
     }
     sounds
 }
 
-pub fn find_noise_file(dir: &Path) -> Option<PathBuf> {
-    let Ok(dir) = dir.read_dir() else {
-        return None;
-    };
-    for entry in dir {
-        let path = entry.unwrap().path();
-        let filename = path.file_name().unwrap().to_str().unwrap();
-        // if it's a noise*, etc file we should try to load it
-        if path.is_file()
-            && (filename.starts_with("noise")
-                || filename.starts_with("whitenoise")
-                || filename.starts_with("pcnoise")
-                || filename.starts_with("background"))
-        {
-            return Some(path);
-        }
-    }
-    None
+/// Files in `dir` that are loaded as clicks.
+pub fn click_files_in(fs: &ClickpackFs, dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs.files_in(dir);
+    files.retain(|path| is_audio_file(path));
+    files
 }
 
-impl Sounds {
-    pub fn from_path(path: &Path, system: *mut FMOD_SYSTEM) -> Self {
-        let mut sounds = Self::default();
-
-        for (dir, clicks) in [
-            ("hardclicks", &mut sounds.hardclicks),
-            ("hardreleases", &mut sounds.hardreleases),
-            ("clicks", &mut sounds.clicks),
-            ("releases", &mut sounds.releases),
-            ("softclicks", &mut sounds.softclicks),
-            ("softreleases", &mut sounds.softreleases),
-            ("microclicks", &mut sounds.microclicks),
-            ("microreleases", &mut sounds.microreleases),
-        ] {
-            let mut pathbuf = path.to_path_buf();
-            pathbuf.push(dir);
-            clicks.extend(read_clicks_in_directory(&pathbuf, system));
-        }
+pub fn is_noise_file(path: &Path) -> bool {
+    let filename = path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or_default();
+    // if it's a noise*, etc file we should try to load it
+    filename.starts_with("noise")
+        || filename.starts_with("whitenoise")
+        || filename.starts_with("pcnoise")
+        || filename.starts_with("background")
+}
 
-        if !sounds.has_sounds() {
-            log::warn!("no sounds found, assuming there's no subdirectories");
-            sounds.clicks = read_clicks_in_directory(path, system);
-        }
+pub fn find_noise_file(fs: &ClickpackFs, dir: &Path) -> Option<PathBuf> {
+    fs.files_in(dir)
+        .into_iter()
+        .find(|path| is_noise_file(path) && is_audio_file(path))
+}
 
-        sounds
+impl Sounds {
+    /// All categories along with their click type.
+    pub fn categories(&self) -> [(ClickType, &Vec<SoundWrapper>); 8] {
+        [
+            (ClickType::HardClick, &self.hardclicks),
+            (ClickType::HardRelease, &self.hardreleases),
+            (ClickType::Click, &self.clicks),
+            (ClickType::Release, &self.releases),
+            (ClickType::SoftClick, &self.softclicks),
+            (ClickType::SoftRelease, &self.softreleases),
+            (ClickType::MicroClick, &self.microclicks),
+            (ClickType::MicroRelease, &self.microreleases),
+        ]
     }
 
-    #[inline]
-    pub fn num_sounds(&self) -> usize {
+    pub fn categories_mut(&mut self) -> [(ClickType, &mut Vec<SoundWrapper>); 8] {
         [
-            &self.hardclicks,
-            &self.hardreleases,
-            &self.clicks,
-            &self.releases,
-            &self.softclicks,
-            &self.softreleases,
-            &self.microclicks,
-            &self.microreleases,
+            (ClickType::HardClick, &mut self.hardclicks),
+            (ClickType::HardRelease, &mut self.hardreleases),
+            (ClickType::Click, &mut self.clicks),
+            (ClickType::Release, &mut self.releases),
+            (ClickType::SoftClick, &mut self.softclicks),
+            (ClickType::SoftRelease, &mut self.softreleases),
+            (ClickType::MicroClick, &mut self.microclicks),
+            (ClickType::MicroRelease, &mut self.microreleases),
         ]
-        .iter()
-        .map(|c| c.len())
-        .sum()
+    }
+
+    /// Number of sounds in the normal categories, without button banks.
+    pub fn num_sounds(&self) -> usize {
+        self.categories()
+            .iter()
+            .map(|(typ, _)| self.num_sounds_in(*typ, None))
+            .sum()
+    }
+
+    /// Number of sounds in the button banks.
+    pub fn num_bank_sounds(&self) -> usize {
+        self.categories()
+            .iter()
+            .flat_map(|(_, clicks)| clicks.iter())
+            .filter(|click| click.button.is_some())
+            .count()
+    }
+
+    /// Number of sounds of a category in the bank of `button`, or in the normal
+    /// categories if it's [None].
+    pub fn num_sounds_in(&self, typ: ClickType, button: Option<Button>) -> usize {
+        self.sounds(typ)
+            .iter()
+            .filter(|click| click.button == button)
+            .count()
     }
 
     fn free_fmod_sounds(&mut self) {
-        for sounds in [
-            &mut self.hardclicks,
-            &mut self.hardreleases,
-            &mut self.clicks,
-            &mut self.releases,
-            &mut self.softclicks,
-            &mut self.softreleases,
-            &mut self.microclicks,
-            &mut self.microreleases,
-        ] {
+        for (_, sounds) in self.categories_mut() {
             for sound in sounds {
                 sound.free();
             }
@@ -423,30 +1234,77 @@ impl Sounds {
 
     #[inline]
     pub fn has_sounds(&self) -> bool {
-        self.num_sounds() > 0
+        self.categories()
+            .iter()
+            .any(|(_, clicks)| !clicks.is_empty())
     }
 
-    pub fn random_sound(&self, typ: ClickType) -> Option<(SoundWrapper, ClickType)> {
-        let thread_rng = &mut rand::thread_rng();
-        for typ in typ.preferred() {
-            let sound = match typ {
-                ClickType::HardClick => self.hardclicks.choose(thread_rng),
-                ClickType::HardRelease => self.hardreleases.choose(thread_rng),
-                ClickType::Click => self.clicks.choose(thread_rng),
-                ClickType::Release => self.releases.choose(thread_rng),
-                ClickType::SoftClick => self.softclicks.choose(thread_rng),
-                ClickType::SoftRelease => self.softreleases.choose(thread_rng),
-                ClickType::MicroClick => self.microclicks.choose(thread_rng),
-                ClickType::MicroRelease => self.microreleases.choose(thread_rng),
-                _ => None,
-            };
-            if let Some(sound) = sound {
-                return Some((sound.clone(), typ));
+    /// Sounds of a single category.
+    pub fn sounds(&self, typ: ClickType) -> &[SoundWrapper] {
+        match typ {
+            ClickType::HardClick => &self.hardclicks,
+            ClickType::HardRelease => &self.hardreleases,
+            ClickType::Click => &self.clicks,
+            ClickType::Release => &self.releases,
+            ClickType::SoftClick => &self.softclicks,
+            ClickType::SoftRelease => &self.softreleases,
+            ClickType::MicroClick => &self.microclicks,
+            ClickType::MicroRelease => &self.microreleases,
+            ClickType::None => &[],
+        }
+    }
+
+    pub fn sounds_mut(&mut self, typ: ClickType) -> Option<&mut Vec<SoundWrapper>> {
+        self.categories_mut()
+            .into_iter()
+            .find(|(t, _)| *t == typ)
+            .map(|(_, sounds)| sounds)
+    }
+
+    /// Pick a sound with the given selection mode, respecting the weight of each
+    /// sound. Categories that are empty or only have sounds with a weight of 0 are
+    /// skipped in favor of the next preferred category. Each category is taken
+    /// from the bank of `button` if it has any sounds there, otherwise from the
+    /// normal categories.
+    pub fn random_sound(
+        &mut self,
+        typ: ClickType,
+        mode: SelectionMode,
+        avoid_last: usize,
+        button: Button,
+    ) -> Option<(SoundWrapper, ClickType)> {
+        for typ in typ.preferred() {
+            if typ == ClickType::None {
+                break;
+            }
+            for bank in [Some(button), None] {
+                if let Some(i) = self.pick_in_bank(typ, mode, avoid_last, bank) {
+                    return Some((self.sounds(typ)[i].clone(), typ));
+                }
             }
         }
         None
     }
 
+    /// Index of a sound of `typ` in the bank of `button`. Fails if there's no
+    /// sounds or all weights are zero.
+    fn pick_in_bank(
+        &mut self,
+        typ: ClickType,
+        mode: SelectionMode,
+        avoid_last: usize,
+        button: Option<Button>,
+    ) -> Option<usize> {
+        // sounds of other banks are never picked
+        let weights: Vec<f32> = self
+            .sounds(typ)
+            .iter()
+            .map(|s| if s.button == button { s.weight } else { 0.0 })
+            .collect();
+        let bank = button.map_or(0, |b| b as usize + 1);
+        self.selectors[bank][typ as usize].pick(mode, &weights, avoid_last, &mut rand::thread_rng())
+    }
+
     pub fn extend_with(&mut self, other: &Self) {
         for (s, o) in [
             (&mut self.hardclicks, &other.hardclicks),
@@ -460,6 +1318,224 @@ impl Sounds {
         ] {
             s.extend_from_slice(o);
         }
+        self.failed.extend_from_slice(&other.failed);
+    }
+}
+
+/// Load the sounds of both players from the folders of a clickpack, see
+/// [layout::map_folders].
+pub fn read_players(
+    fs: &ClickpackFs,
+    folders: &[FolderMapping],
+    loader: &SoundLoader,
+) -> (Sounds, Sounds) {
+    if let Some(progress) = &loader.progress {
+        progress.add_files(folders.iter().map(|folder| folder.files.len()).sum());
+    }
+
+    let mut players = (Sounds::default(), Sounds::default());
+    for folder in folders {
+        if loader.is_cancelled() {
+            break;
+        }
+        let sounds = if folder.player2 {
+            &mut players.1
+        } else {
+            &mut players.0
+        };
+        let mut clicks = read_clicks(fs, &folder.files, loader, &mut sounds.failed);
+        // button banks are stored in the same categories, see [Sounds::random_sound]
+        for click in &mut clicks {
+            click.button = folder.button;
+        }
+        if let Some(category) = sounds.sounds_mut(folder.typ) {
+            category.extend(clicks);
+        }
+    }
+    players
+}
+
+/// The noise file of a clickpack, if it has one. Noise in the root is preferred
+/// over noise in the player folders.
+pub fn find_clickpack_noise(fs: &ClickpackFs, root: &Path, rules: &FolderRules) -> Option<PathBuf> {
+    layout::player_dirs(fs, root, rules)
+        .iter()
+        .find_map(|(dir, _)| find_noise_file(fs, dir))
+}
+
+/// Load a noise file. Noise is looped, so it isn't trimmed or faded like clicks.
+pub fn load_noise(
+    fs: &ClickpackFs,
+    path: &Path,
+    loader: &SoundLoader,
+    noise_loop: &NoiseLoopSettings,
+) -> Result<SoundWrapper> {
+    let mut sound = fs.decode(None, loader.cache.as_ref(), path)?;
+    if loader.resample {
+        // resample before crossfading, resampling the loop on its own would
+        // bring back the click at the loop point
+        let frames = dsp::resample_frames(&sound.frames, sound.sample_rate());
+        sound = Sound::from_frames(dsp::CANONICAL_SAMPLE_RATE, &frames);
+    }
+    let sound = noise_loop.make_loop(sound);
+    Ok(SoundWrapper::from_decoded(loader, sound, path))
+}
+
+/// A clickpack that is loaded without touching the current one, so the current
+/// clickpack keeps playing until it's swapped in with [Bot::swap_clickpack].
+pub struct LoadedClickpack {
+    pub path: PathBuf,
+    pub players: (Sounds, Sounds),
+    pub noise: Option<SoundWrapper>,
+    pub manifest: Option<ClickpackManifest>,
+    /// Folders the sounds were loaded from.
+    pub folders: Vec<FolderMapping>,
+}
+
+impl LoadedClickpack {
+    pub fn load(
+        path: &Path,
+        loader: &SoundLoader,
+        conf: &Config,
+        loudness_cache: &Mutex<LoudnessCache>,
+        with_noise: bool,
+    ) -> Result<Self> {
+        // open the folder or zip archive
+        let (fs, root) = ClickpackFs::open(path, &conf.folder_rules)?;
+        let manifest = ClickpackManifest::read(&fs, &root)?;
+
+        // load for both players
+        let folders = layout::map_folders(&fs, &root, &conf.folder_rules);
+        let mut clickpack = Self {
+            path: path.to_path_buf(),
+            players: read_players(&fs, &folders, loader),
+            noise: None,
+            manifest: None,
+            folders,
+        };
+        if loader.is_cancelled() || !clickpack.has_sounds() {
+            clickpack.free();
+            anyhow::ensure!(!loader.is_cancelled(), "loading was cancelled");
+            anyhow::bail!("no sounds found in clickpack");
+        }
+
+        if conf.auto_sort.enabled {
+            autosort::sort_unsorted(
+                &mut clickpack.players,
+                &mut clickpack.folders,
+                &conf.auto_sort,
+            );
+        }
+
+        if let Some(noise) =
+            find_clickpack_noise(&fs, &root, &conf.folder_rules).filter(|_| with_noise)
+        {
+            clickpack.noise = load_noise(&fs, &noise, loader, &conf.noise_loop)
+                .map_err(|e| log::error!("failed to load noise {noise:?}: {e}"))
+                .ok();
+        }
+
+        // apply clickpack.json if there is one
+        if let Some(manifest) = manifest {
+            manifest.apply_to_sounds(&root, &mut clickpack.players.0);
+            manifest.apply_to_sounds(&root, &mut clickpack.players.1);
+            clickpack.manifest = Some(manifest);
+        }
+
+        let normalization = &conf.normalization;
+        if normalization.enabled {
+            // processing changes the loudness of the decoded sounds
+            let salt = loader
+                .processing
+                .as_ref()
+                .map(ProcessingSettings::cache_salt)
+                .unwrap_or_default();
+            let (p1, p2) = &mut clickpack.players;
+            loudness::normalize(
+                normalization,
+                [p1, p2],
+                &fs,
+                &mut loudness_cache.lock().unwrap(),
+                &salt,
+            );
+        }
+
+        Ok(clickpack)
+    }
+
+    #[inline]
+    pub fn has_sounds(&self) -> bool {
+        self.players.0.has_sounds() || self.players.1.has_sounds()
+    }
+
+    /// Replace the player 2 sounds with the sounds of another clickpack. Its
+    /// player 1 sounds are used, or its player 2 sounds if it only has those.
+    pub fn use_player2_from(&mut self, mut other: Self) {
+        let (p1, p2) = std::mem::take(&mut other.players);
+        let from_player2 = !p1.has_sounds();
+        let (mut sounds, mut unused) = if from_player2 { (p2, p1) } else { (p1, p2) };
+        unused.free_fmod_sounds();
+        std::mem::swap(&mut self.players.1, &mut sounds);
+        sounds.free_fmod_sounds();
+
+        self.folders.retain(|folder| !folder.player2);
+        self.folders.extend(
+            other
+                .folders
+                .drain(..)
+                .filter(|folder| folder.player2 == from_player2)
+                .map(|folder| FolderMapping {
+                    player2: true,
+                    ..folder
+                }),
+        );
+        other.free();
+    }
+
+    /// Release the FMOD sounds, for clickpacks that are never swapped in.
+    pub fn free(&mut self) {
+        self.players.0.free_fmod_sounds();
+        self.players.1.free_fmod_sounds();
+        if let Some(noise) = &mut self.noise {
+            noise.free();
+        }
+    }
+}
+
+pub enum NoiseHandle {
+    Kittyaudio(SoundHandle),
+    Fmod(*mut FMOD_CHANNEL),
+}
+
+/// A noise that is playing on one of the backends.
+pub struct NoiseVoice {
+    pub handle: NoiseHandle,
+    /// Volume without modulation.
+    pub volume: f32,
+}
+
+impl NoiseVoice {
+    fn set_gain(&self, gain: f32) {
+        let volume = self.volume * gain;
+        match &self.handle {
+            NoiseHandle::Kittyaudio(handle) => handle.set_volume(volume),
+            NoiseHandle::Fmod(channel) => unsafe {
+                FMOD_Channel_SetVolume(*channel, volume);
+            },
+        }
+    }
+
+    fn stop(self) {
+        match self.handle {
+            NoiseHandle::Kittyaudio(handle) => {
+                handle.set_playback_rate(PlaybackRate::Factor(1.0));
+                handle.set_loop_enabled(false);
+                handle.seek_to_end();
+            }
+            NoiseHandle::Fmod(channel) => unsafe {
+                FMOD_Channel_Stop(channel);
+            },
+        }
     }
 }
 
@@ -486,7 +1562,7 @@ impl Default for Shortcuts {
     }
 }
 
-#[derive(Serialize, Deserialize, Default, Clone)]
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
 pub enum Clickpack {
     #[default]
     None,
@@ -494,6 +1570,67 @@ pub enum Clickpack {
     Path(PathBuf),
 }
 
+impl Clickpack {
+    /// Path of the clickpack, clickpacks by name are looked up in `clickpacks`.
+    pub fn resolve(&self, clickpacks: &[PathBuf]) -> Option<PathBuf> {
+        match self {
+            Self::None => None,
+            Self::Name(name) => clickpacks
+                .iter()
+                .find(|path| path.file_name().is_some_and(|n| *n == **name))
+                .cloned(),
+            Self::Path(path) => Some(path.clone()),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::None => String::new(),
+            Self::Name(name) => name.clone(),
+            Self::Path(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Clickpack that plays along with the main one. Layers only add clicks, their
+/// noise is not loaded.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClickpackLayer {
+    pub clickpack: Clickpack,
+    /// Chance of picking this clickpack in [LayerMode::Random].
+    pub weight: f32,
+    /// Volume multiplier of the clicks.
+    pub gain: f32,
+}
+
+impl ClickpackLayer {
+    pub fn new(clickpack: Clickpack) -> Self {
+        Self {
+            clickpack,
+            weight: 1.0,
+            gain: 1.0,
+        }
+    }
+}
+
+/// Sounds of a loaded [ClickpackLayer]. The weight and gain are copied from
+/// [Env::layers], so they don't have to be looked up for every click.
+pub struct LayerSounds {
+    pub players: (Sounds, Sounds),
+    pub weight: f32,
+    pub gain: f32,
+}
+
+impl LayerSounds {
+    fn free_fmod_sounds(&mut self) {
+        self.players.0.free_fmod_sounds();
+        self.players.1.free_fmod_sounds();
+    }
+}
+
 fn skip_serializing_selected_device(device: &str) -> bool {
     if cfg!(feature = "special") {
         let is_default = if let Ok(name) = Device::Default.name() {
@@ -516,6 +1653,20 @@ pub struct Env {
         skip_serializing_if = "skip_serializing_selected_device"
     )]
     pub selected_device: String,
+    /// Weight of the main clickpack, see [ClickpackLayer::weight].
+    #[serde(default = "float_one")]
+    pub clickpack_weight: f32,
+    /// Gain of the main clickpack, see [ClickpackLayer::gain].
+    #[serde(default = "float_one")]
+    pub clickpack_gain: f32,
+    #[serde(default = "Vec::new")]
+    pub layers: Vec<ClickpackLayer>,
+    #[serde(default = "LayerMode::default")]
+    pub layer_mode: LayerMode,
+    /// Clickpack for player 2. [Clickpack::None] uses the player 2 folder of the
+    /// main clickpack.
+    #[serde(default = "Clickpack::default")]
+    pub player2_clickpack: Clickpack,
 }
 
 impl Default for Env {
@@ -524,6 +1675,11 @@ impl Default for Env {
             version: built_info::PKG_VERSION.to_string(),
             clickpack: Clickpack::None,
             selected_device: String::new(),
+            clickpack_weight: 1.0,
+            clickpack_gain: 1.0,
+            layers: vec![],
+            layer_mode: LayerMode::default(),
+            player2_clickpack: Clickpack::None,
         }
     }
 }
@@ -579,6 +1735,11 @@ fn default_buffer_size() -> u32 {
     512
 }
 
+#[inline]
+fn default_avoid_last() -> usize {
+    2
+}
+
 #[inline]
 fn float_one<Num: emath::Numeric>() -> Num {
     Num::from_f64(1.0)
@@ -637,6 +1798,38 @@ pub struct Config {
     pub use_minhook: bool,
     #[serde(default = "bool::default")]
     pub use_old_egui_hook: bool,
+    #[serde(default = "true_value")]
+    pub use_clickpack_settings: bool,
+    #[serde(default = "SelectionMode::default")]
+    pub selection_mode: SelectionMode,
+    #[serde(default = "default_avoid_last")]
+    pub avoid_last: usize,
+    #[serde(default = "ProcessingSettings::default")]
+    pub processing: ProcessingSettings,
+    #[serde(default = "NormalizationSettings::default")]
+    pub normalization: NormalizationSettings,
+    #[serde(default = "CacheSettings::default")]
+    pub cache: CacheSettings,
+    #[serde(default = "bool::default")]
+    pub auto_reload_clickpack: bool,
+    #[serde(default = "NoiseLoopSettings::default")]
+    pub noise_loop: NoiseLoopSettings,
+    #[serde(default = "true_value")]
+    pub use_clickpack_noise: bool,
+    #[serde(default = "Vec::new")]
+    pub noise_layers: Vec<NoiseLayer>,
+    #[serde(default = "NoiseModulationSettings::default")]
+    pub noise_modulation: NoiseModulationSettings,
+    #[serde(default = "NoiseGeneratorSettings::default")]
+    pub noise_generator: NoiseGeneratorSettings,
+    #[serde(default = "FolderRules::default")]
+    pub folder_rules: FolderRules,
+    #[serde(default = "AutoSortSettings::default")]
+    pub auto_sort: AutoSortSettings,
+    #[serde(default = "BuilderSettings::default")]
+    pub builder: BuilderSettings,
+    #[serde(default = "SimulatorSettings::default")]
+    pub simulator: SimulatorSettings,
 }
 
 impl Config {
@@ -672,6 +1865,22 @@ impl Default for Config {
             hook_wait: false,
             use_minhook: true,
             use_old_egui_hook: false,
+            use_clickpack_settings: true,
+            selection_mode: SelectionMode::default(),
+            avoid_last: default_avoid_last(),
+            processing: ProcessingSettings::default(),
+            normalization: NormalizationSettings::default(),
+            cache: CacheSettings::default(),
+            auto_reload_clickpack: false,
+            noise_loop: NoiseLoopSettings::default(),
+            use_clickpack_noise: true,
+            noise_layers: vec![],
+            noise_modulation: NoiseModulationSettings::default(),
+            noise_generator: NoiseGeneratorSettings::default(),
+            folder_rules: FolderRules::default(),
+            auto_sort: AutoSortSettings::default(),
+            builder: BuilderSettings::default(),
+            simulator: SimulatorSettings::default(),
         }
     }
 }
@@ -722,6 +1931,8 @@ pub struct Bot {
     pub playlayer: PlayLayer,
     pub prev_time: f64,
     pub is_loading_clickpack: bool,
+    /// Progress of the clickpack that is being loaded.
+    pub load_progress: Option<Arc<LoadProgress>>,
     pub num_sounds: (usize, usize),
     pub selected_clickpack: String,
     pub devices: Arc<Mutex<Vec<String>>>,
@@ -733,22 +1944,60 @@ pub struct Bot {
     pub prev_volume: f32,
     pub prev_spam_offset: f32,
     pub buffer_size_changed: bool,
-    pub noise_sound: Option<SoundHandle>,
+    /// Noises that are playing, see [Bot::play_noise].
+    pub noise_voices: Vec<NoiseVoice>,
+    pub noise_envelope: ActivityEnvelope,
+    /// Volume multiplier of the noise from [Config::noise_modulation].
+    pub noise_gain: f32,
     pub show_alternate_hook_warning: bool,
     pub did_reset_config: bool,
     pub clickpacks: Vec<PathBuf>,
     pub last_clickpack_reload: Instant,
+    /// [None] if the watcher couldn't be started, clickpacks are polled instead.
+    pub watcher: Option<ClickpackWatcher>,
     pub level_start: Instant,
     pub used_alternate_hook: bool,
     pub system: *mut FMOD_SYSTEM,
     pub channel: *mut FMOD_CHANNEL,
     pub env: Env,
     pub toast_queue: Arc<Mutex<Vec<Toast>>>,
-    pub fmod_noise_sound: *mut FMOD_CHANNEL,
     pub show_fmod_buffersize_warn: bool,
     pub startup_buffer_size: u32,
     pub used_minhook: bool,
     pub used_old_egui_hook: bool,
+    pub manifest: Option<ClickpackManifest>,
+    /// Path of the currently loaded clickpack.
+    pub clickpack_path: Option<PathBuf>,
+    /// Folders the current clickpack was loaded from.
+    pub folders: Vec<FolderMapping>,
+    pub loudness_cache: Arc<Mutex<LoudnessCache>>,
+    /// Result of the last clickpack analysis.
+    pub clickpack_report: Option<ClickpackReport>,
+    pub is_analyzing_clickpack: bool,
+    /// Loaded noise layer files, by path relative to [NOISE_LIBRARY_DIR].
+    pub library_noise: HashMap<PathBuf, SoundWrapper>,
+    /// Noise layer files that finished loading on another thread, see
+    /// [Bot::apply_loaded_noise].
+    pub loaded_noise: Mutex<Vec<(PathBuf, SoundWrapper)>>,
+    /// Files in [NOISE_LIBRARY_DIR].
+    pub noise_library: Vec<PathBuf>,
+    /// Output of [Config::noise_generator], if it's enabled.
+    pub generated_noise: Option<SoundWrapper>,
+    /// Loaded clickpack layers (see [Env::layers]) by path.
+    pub layer_sounds: HashMap<PathBuf, LayerSounds>,
+    /// Clickpack layers that finished loading on another thread, see
+    /// [Bot::apply_loaded_layers].
+    pub loaded_layers: Mutex<Vec<(PathBuf, (Sounds, Sounds))>>,
+    /// Clickpack noise that is played from the preview panel.
+    pub preview_noise: Option<NoiseVoice>,
+    /// Sound shown in the preview panel: player 2, category and index.
+    pub preview_selected: Option<(bool, ClickType, usize)>,
+    /// Actions of the settings simulator that are still to be played and when,
+    /// see [Bot::play_simulation]. Empty if it isn't running.
+    pub simulation: VecDeque<(Instant, SimulatedAction)>,
+    /// Clickpack that finished loading on another thread. It's swapped in by
+    /// [Bot::apply_pending_clickpack] on the game thread, where sounds are played.
+    pub pending_clickpack: Mutex<Option<LoadedClickpack>>,
 }
 
 impl Default for Bot {
@@ -766,6 +2015,7 @@ impl Default for Bot {
             playlayer: PlayLayer::from_address(0),
             prev_time: 0.0,
             is_loading_clickpack: false,
+            load_progress: None,
             num_sounds: (0, 0),
             selected_clickpack: String::new(),
             devices: Arc::new(Mutex::new(vec![])),
@@ -777,35 +2027,76 @@ impl Default for Bot {
             prev_volume: f32::NAN,
             prev_spam_offset: f32::NAN,
             buffer_size_changed: false,
-            noise_sound: None,
+            noise_voices: vec![],
+            noise_envelope: ActivityEnvelope::default(),
+            noise_gain: 1.0,
             show_alternate_hook_warning: false,
             did_reset_config: false,
             clickpacks: vec![],
             last_clickpack_reload: Instant::now(),
+            watcher: None,
             level_start: Instant::now(),
             used_alternate_hook: use_alternate_hook,
             system: std::ptr::null_mut(),
             channel: std::ptr::null_mut(),
             env: Env::load(),
             toast_queue: Arc::new(Mutex::new(vec![])),
-            fmod_noise_sound: std::ptr::null_mut(),
             show_fmod_buffersize_warn: false,
             startup_buffer_size,
             used_minhook,
             used_old_egui_hook,
+            manifest: None,
+            clickpack_path: None,
+            folders: vec![],
+            loudness_cache: Arc::new(Mutex::new(LoudnessCache::load())),
+            clickpack_report: None,
+            is_analyzing_clickpack: false,
+            library_noise: HashMap::new(),
+            loaded_noise: Mutex::new(vec![]),
+            noise_library: vec![],
+            generated_noise: None,
+            layer_sounds: HashMap::new(),
+            loaded_layers: Mutex::new(vec![]),
+            preview_noise: None,
+            preview_selected: None,
+            simulation: VecDeque::new(),
+            pending_clickpack: Mutex::new(None),
         }
     }
 }
 
-const PLAYER_DIRNAMES: [(&str, &str); 7] = [
-    ("player1", "player2"),
-    ("player 1", "player 2"),
-    ("sounds1", "sounds2"),
-    ("sounds 1", "sounds 2"),
-    ("p1", "p2"),
-    ("1", "2"),
-    ("", ""),
-];
+/// Open a folder in the file explorer.
+fn open_in_explorer(dir: &str) {
+    match Command::new("explorer").arg(dir).spawn() {
+        // wait for it on another thread, so it doesn't stay around as a zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => log::error!("failed to open {dir} in explorer: {e}"),
+    }
+}
+
+/// Draw the waveform of a sound, the louder channel of every frame.
+fn show_waveform(ui: &mut egui::Ui, frames: &[Frame]) {
+    let size = vec2(ui.available_width(), 60.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    if frames.is_empty() {
+        return;
+    }
+    let stroke = egui::Stroke::new(1.0, ui.visuals().text_color());
+    let columns = rect.width().max(1.0) as usize;
+    let per_column = frames.len().div_ceil(columns).max(1);
+    for (x, chunk) in frames.chunks(per_column).enumerate() {
+        let (min, max) = chunk.iter().fold((0.0f32, 0.0f32), |(min, max), f| {
+            (min.min(f.left.min(f.right)), max.max(f.left.max(f.right)))
+        });
+        let x = rect.left() + x as f32;
+        let y = |v: f32| rect.center().y - v.clamp(-1.0, 1.0) * rect.height() / 2.0;
+        painter.line_segment([pos2(x, y(max)), pos2(x, y(min))], stroke);
+    }
+}
 
 fn help_text<R>(ui: &mut egui::Ui, help: &str, add_contents: impl FnOnce(&mut egui::Ui) -> R) {
     if help.is_empty() {
@@ -874,7 +2165,13 @@ fn drag_value<Num: emath::Numeric>(
 impl Bot {
     fn unload_clickpack(&mut self) {
         log::debug!("unloading clickpack");
+        self.stop_simulation();
+        if let Some(mut pending) = self.pending_clickpack.lock().unwrap().take() {
+            pending.free();
+        }
         self.num_sounds = (0, 0);
+        self.stop_noise();
+        self.stop_preview_noise();
         self.players.0.free_fmod_sounds();
         self.players.1.free_fmod_sounds();
         if let Some(noise) = &mut self.noise {
@@ -882,43 +2179,51 @@ impl Bot {
         }
         self.players = (Sounds::default(), Sounds::default());
         self.noise = None;
-        if let Some(noise_sound) = self.noise_sound.take() {
-            noise_sound.seek_to_end();
-            noise_sound.set_loop_enabled(false);
-            noise_sound.set_playback_rate(PlaybackRate::Factor(0.0));
-        }
+        self.manifest = None;
+        self.folders.clear();
+        self.clickpack_path = None;
+        self.clickpack_report = None;
+        self.preview_selected = None;
         self.selected_clickpack.clear();
+        // noise layers keep playing
+        self.play_noise();
     }
 
-    pub fn load_clickpack(&mut self, clickpack_dir: &Path) -> Result<()> {
-        // reset current clickpack
-        self.unload_clickpack();
-
-        for player_dirnames in PLAYER_DIRNAMES {
-            let mut player1_path = clickpack_dir.to_path_buf();
-            player1_path.push(player_dirnames.0);
-            let mut player2_path = clickpack_dir.to_path_buf();
-            player2_path.push(player_dirnames.1);
-
-            // load for both players
-            self.players
-                .0
-                .extend_with(&Sounds::from_path(&player1_path, self.system));
-            self.load_noise(&player1_path);
-            if !player_dirnames.1.is_empty() {
-                self.players
-                    .1
-                    .extend_with(&Sounds::from_path(&player2_path, self.system));
-                self.load_noise(&player2_path);
-            }
-        }
-
-        self.load_noise(clickpack_dir);
-
-        anyhow::ensure!(self.has_sounds(), "no sounds found in clickpack");
+    /// Swap in the clickpack that finished loading, if there is one. Must be
+    /// called on the game thread, so no click is playing from the old sounds
+    /// while they're replaced.
+    fn apply_pending_clickpack(&mut self) {
+        // a loader that holds the lock reads the current clickpack
+        let _lock = match LOAD_LOCK.try_lock() {
+            Ok(lock) => lock,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        let Some(clickpack) = self.pending_clickpack.lock().unwrap().take() else {
+            return;
+        };
+        self.swap_clickpack(clickpack);
+    }
 
-        self.num_sounds = (self.players.0.num_sounds(), self.players.1.num_sounds());
-        self.selected_clickpack = clickpack_dir
+    /// Replace the current clickpack with a loaded one. The old clickpack keeps
+    /// playing until this is called, so there's no silent gap.
+    fn swap_clickpack(&mut self, clickpack: LoadedClickpack) {
+        self.stop_simulation();
+        self.stop_preview_noise();
+        self.preview_selected = None;
+        let mut old_players = std::mem::replace(&mut self.players, clickpack.players);
+        let mut old_noise = std::mem::replace(&mut self.noise, clickpack.noise);
+        self.manifest = clickpack.manifest;
+        self.folders = clickpack.folders;
+        // button banks are counted too, a clickpack can have no normal sounds
+        self.num_sounds = (
+            self.players.0.num_sounds() + self.players.0.num_bank_sounds(),
+            self.players.1.num_sounds() + self.players.1.num_bank_sounds(),
+        );
+        self.clickpack_path = Some(clickpack.path.clone());
+        self.clickpack_report = None;
+        self.selected_clickpack = clickpack
+            .path
             .file_name()
             .unwrap()
             .to_str()
@@ -933,34 +2238,121 @@ impl Bot {
         log::info!("{} player 2 sounds", self.num_sounds.1);
         log::info!("has noise: {}", self.noise.is_some());
 
+        // switch to the new noise before the old one is released. releasing an
+        // fmod sound stops the channels that are playing it, kittyaudio sounds
+        // share their frames with the mixer
         self.play_noise();
+        old_players.0.free_fmod_sounds();
+        old_players.1.free_fmod_sounds();
+        if let Some(noise) = &mut old_noise {
+            noise.free();
+        }
+    }
 
-        Ok(())
+    /// Sound loader for clicks with the current processing settings.
+    fn sound_loader(&self) -> SoundLoader {
+        SoundLoader::new(self.system)
+            .with_processing(self.conf.processing.clone())
+            .with_cache(self.pcm_cache())
+            .with_resampling(self.conf.use_fmod)
     }
 
-    fn load_noise(&mut self, dir: &Path) {
-        let Some(path) = find_noise_file(dir) else {
-            return;
-        };
-        // try to load noise
-        self.noise = SoundWrapper::from_path(self.system, &path).ok();
+    #[inline]
+    fn pcm_cache(&self) -> Option<PcmCache> {
+        self.conf.cache.enabled.then(PcmCache::default)
+    }
+
+    /// Timings with the clickpack manifest applied on top.
+    pub fn timings(&self) -> Timings {
+        let mut timings = self.conf.timings.clone();
+        if let Some(manifest) = self.active_manifest() {
+            manifest.timings.apply(&mut timings);
+        }
+        timings
+    }
+
+    /// Pitch settings with the clickpack manifest applied on top.
+    pub fn pitch(&self) -> Pitch {
+        let mut pitch = self.conf.pitch.clone();
+        if let Some(manifest) = self.active_manifest() {
+            manifest.apply_pitch(&mut pitch);
+        }
+        pitch
+    }
+
+    /// Volume settings with the clickpack manifest applied on top.
+    pub fn volume_settings(&self) -> VolumeSettings {
+        let mut vol = self.conf.volume_settings.clone();
+        if let Some(manifest) = self.active_manifest() {
+            manifest.apply_volume_settings(&mut vol);
+        }
+        vol
+    }
+
+    #[inline]
+    fn active_manifest(&self) -> Option<&ClickpackManifest> {
+        self.manifest
+            .as_ref()
+            .filter(|_| self.conf.use_clickpack_settings)
     }
 
     pub fn has_sounds(&self) -> bool {
         self.players.0.has_sounds() || self.players.1.has_sounds()
     }
 
-    fn get_random_click(&self, typ: ClickType, player2: bool) -> (SoundWrapper, ClickType) {
-        if player2 {
-            self.players
-                .1
-                .random_sound(typ)
-                .unwrap_or_else(|| self.players.0.random_sound(typ).unwrap())
-        } else {
-            self.players
-                .0
-                .random_sound(typ)
-                .unwrap_or_else(|| self.players.1.random_sound(typ).unwrap())
+    /// Clicks to play for an action. There is one click, or one for every
+    /// clickpack in [LayerMode::Layered]. Gains of the clickpacks are applied.
+    fn get_random_clicks(
+        &mut self,
+        typ: ClickType,
+        player2: bool,
+        button: Button,
+    ) -> Vec<(SoundWrapper, ClickType)> {
+        let (mode, avoid_last) = (self.conf.selection_mode, self.conf.avoid_last);
+        let pick = |players: &mut (Sounds, Sounds), gain: f32| {
+            let (first, second) = if player2 {
+                (&mut players.1, &mut players.0)
+            } else {
+                (&mut players.0, &mut players.1)
+            };
+            let (mut click, typ) = first
+                .random_sound(typ, mode, avoid_last, button)
+                .or_else(|| second.random_sound(typ, mode, avoid_last, button))?;
+            click.gain *= gain;
+            Some((click, typ))
+        };
+
+        // the main clickpack and the loaded layers, with their weight and gain
+        let mut sources = vec![(
+            &mut self.players,
+            self.env.clickpack_weight,
+            self.env.clickpack_gain,
+        )];
+        for layer in self.layer_sounds.values_mut() {
+            sources.push((&mut layer.players, layer.weight, layer.gain));
+        }
+
+        match self.env.layer_mode {
+            LayerMode::Random => {
+                let indices: Vec<usize> = (0..sources.len()).collect();
+                let i = indices
+                    .choose_weighted(&mut rand::thread_rng(), |&i| {
+                        let (players, weight, _) = &sources[i];
+                        if players.0.has_sounds() || players.1.has_sounds() {
+                            *weight
+                        } else {
+                            0.0
+                        }
+                    })
+                    .copied()
+                    .unwrap_or(0); // all weights are zero, use the main clickpack
+                let (players, _, gain) = sources.swap_remove(i);
+                pick(players, gain).into_iter().collect()
+            }
+            LayerMode::Layered => sources
+                .into_iter()
+                .filter_map(|(players, _, gain)| pick(players, gain))
+                .collect(),
         }
     }
 
@@ -982,7 +2374,7 @@ impl Bot {
     }
 
     pub unsafe fn init_fmod(&mut self) -> Result<()> {
-        const SYSTEM_SAMPLERATE: i32 = 48_000;
+        const SYSTEM_SAMPLERATE: i32 = dsp::CANONICAL_SAMPLE_RATE as i32;
         log::info!("initializing fmod system");
         if !self.system.is_null() {
             self.release_fmod();
@@ -1083,6 +2475,14 @@ impl Bot {
         let _ = self
             .reload_clickpacks()
             .map_err(|e| log::error!("failed to reload clickpacks: {e}"));
+        self.watcher = ClickpackWatcher::new(Path::new(".zcb/clickpacks"))
+            .map_err(|e| log::error!("failed to watch clickpacks, polling instead: {e}"))
+            .ok();
+
+        // load noise layers, they don't depend on the clickpack
+        self.regenerate_noise();
+        self.refresh_noise_library();
+        self.load_noise_layers();
 
         // check env
         let toast_queue = self.toast_queue.clone();
@@ -1125,6 +2525,7 @@ impl Bot {
             }
             Clickpack::None => log::info!("env.json doesn't specify a clickpack"),
         }
+        self.load_layers();
 
         // init game hooks
         log::debug!("initializing hooks");
@@ -1140,18 +2541,21 @@ impl Bot {
 
     fn get_pitch(&self) -> f64 {
         if self.conf.pitch_enabled {
-            rand::thread_rng().gen_range(self.conf.pitch.from..=self.conf.pitch.to)
+            let pitch = self.pitch();
+            rand::thread_rng().gen_range(pitch.from..=pitch.to)
         } else {
             1.0
         }
     }
 
     pub fn on_init(&mut self) {
+        // the simulator would play clicks in between the ones of the level
+        self.stop_simulation();
         self.prev_time = 0.0;
         self.prev_click_type = ClickType::None;
         self.prev_resolved_click_type = ClickType::None;
         self.prev_pitch = 0.0;
-        self.prev_volume = self.conf.volume_settings.global_volume;
+        self.prev_volume = self.volume_settings().global_volume;
         self.prev_spam_offset = 0.0;
         self.level_start = Instant::now();
     }
@@ -1160,7 +2564,7 @@ impl Bot {
         self.level_start = Instant::now();
     }
 
-    pub fn on_action(&mut self, push: bool, player2: bool) {
+    pub fn on_action(&mut self, push: bool, player2: bool, button: Button) {
         if self.num_sounds == (0, 0) || self.playlayer.is_null() || !self.conf.enabled {
             return;
         }
@@ -1177,48 +2581,36 @@ impl Bot {
             return;
         }
 
-        let now = self.time();
+        self.play_action(push, player2, button, self.time());
+    }
+
+    /// Play the click of an action that happened `now` seconds into the level,
+    /// see [Bot::time]. [Bot::on_action] decides if an action is played at all.
+    pub fn play_action(&mut self, push: bool, player2: bool, button: Button, now: f64) {
+        self.noise_envelope.trigger(Instant::now());
         let dt = (now - self.prev_time).abs() as f32;
-        let click_type = ClickType::from_time(push, dt, &self.conf.timings);
+        let click_type = ClickType::from_time(push, dt, &self.timings());
         let use_fmod = self.conf.use_fmod;
 
-        // get click
-        let (mut click, resolved_click_type) = self.get_random_click(click_type, player2);
+        // get clicks
+        let clicks = self.get_random_clicks(click_type, player2, button);
+        let Some(resolved_click_type) = clicks.first().map(|(_, typ)| *typ) else {
+            return;
+        };
         let pitch = self.get_pitch() * self.conf.click_speedhack;
         // if self.conf.sync_speed_with_game {
         //     pitch *= gd_audio_pitch() as f64;
         // }
-        if !use_fmod {
-            click.set_playback_rate(PlaybackRate::Factor(pitch));
-        }
 
-        // compute & change volume
+        // calculate spam volume change
+        let vol = self.volume_settings();
+        if (!resolved_click_type.is_release() || vol.change_releases_volume)
+            && dt < vol.spam_time
+            && vol.enabled
         {
-            let vol = &self.conf.volume_settings;
-            let mut volume = 1.0;
-            if vol.volume_var != 0.0 {
-                volume += rand::thread_rng().gen_range(-vol.volume_var..=vol.volume_var);
-            }
-
-            // calculate spam volume change
-            if (!resolved_click_type.is_release() || vol.change_releases_volume)
-                && dt < vol.spam_time
-                && vol.enabled
-            {
-                let offset = (vol.spam_time - dt) * vol.spam_vol_offset_factor;
-                self.prev_spam_offset = offset;
-                volume -= offset.min(vol.max_spam_vol_offset);
-            } else {
-                self.prev_spam_offset = 0.0;
-            }
-
-            // multiply by global volume after all of the changes
-            volume *= vol.global_volume;
-
-            if !use_fmod {
-                click.set_volume(volume);
-            }
-            self.prev_volume = volume;
+            self.prev_spam_offset = (vol.spam_time - dt) * vol.spam_vol_offset_factor;
+        } else {
+            self.prev_spam_offset = 0.0;
         }
 
         // stop all playing sounds (acb behaviour)
@@ -1226,35 +2618,59 @@ impl Bot {
             && self.conf.cut_sounds
             && (!click_type.is_release() || self.conf.cut_by_releases)
         {
+            let noise_lens: Vec<usize> = self
+                .noise_voices
+                .iter()
+                .chain(&self.preview_noise)
+                .filter_map(|voice| match &voice.handle {
+                    NoiseHandle::Kittyaudio(handle) => Some(handle.guard().frames.len()),
+                    NoiseHandle::Fmod(_) => None,
+                })
+                .collect();
             for sound in &self.mixer.renderer.guard().sounds {
-                // check if this is the noise sound, we don't want to stop it
-                let sound_len = sound.guard().frames.len();
-                if let Some(noise_sound) = &self.noise_sound {
-                    if noise_sound.guard().frames.len() == sound_len {
-                        continue;
-                    }
+                // check if this is a noise sound, we don't want to stop it
+                if noise_lens.contains(&sound.guard().frames.len()) {
+                    continue;
                 }
 
                 // kis!!
                 sound.seek_to_end();
             }
         }
-        if !use_fmod {
-            self.mixer.play(click.sound);
-        } else {
-            unsafe {
-                FMOD_System_PlaySound(
-                    self.system,
-                    click.fmod_sound,
-                    std::ptr::null_mut(),
-                    0,
-                    &mut self.channel,
-                );
-                FMOD_Channel_SetPitch(self.channel, pitch as f32);
-                FMOD_Channel_SetVolume(self.channel, self.prev_volume);
-                FMOD_System_Update(self.system);
+
+        for (mut click, _) in clicks {
+            // compute volume
+            let mut volume = click.gain;
+            if vol.volume_var != 0.0 {
+                volume += rand::thread_rng().gen_range(-vol.volume_var..=vol.volume_var);
+            }
+            volume -= self.prev_spam_offset.min(vol.max_spam_vol_offset);
+
+            // multiply by global volume after all of the changes
+            volume *= vol.global_volume;
+            self.prev_volume = volume;
+
+            if !use_fmod {
+                click.set_playback_rate(PlaybackRate::Factor(pitch));
+                click.set_volume(volume);
+                self.mixer.play(click.sound);
+            } else {
+                unsafe {
+                    FMOD_System_PlaySound(
+                        self.system,
+                        click.fmod_sound,
+                        std::ptr::null_mut(),
+                        0,
+                        &mut self.channel,
+                    );
+                    FMOD_Channel_SetPitch(self.channel, pitch as f32);
+                    FMOD_Channel_SetVolume(self.channel, volume);
+                }
             }
         }
+        if use_fmod {
+            unsafe { FMOD_System_Update(self.system) };
+        }
         self.prev_time = now;
         self.prev_click_type = click_type;
         self.prev_resolved_click_type = resolved_click_type;
@@ -1292,13 +2708,72 @@ impl Bot {
         for entry in dir {
             let entry = entry?;
             let path = entry.path();
-            if path.is_dir() {
+            if path.is_dir() || is_zip_file(&path) {
                 self.clickpacks.push(path);
             }
         }
         Ok(())
     }
 
+    /// Refresh the clickpack list when something changes on disk, and reload the
+    /// current clickpack if its files changed.
+    fn handle_clickpack_changes(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        watcher.watch_clickpack(self.clickpack_path.as_deref());
+        let changes = watcher.changes();
+        if changes.is_empty() {
+            return;
+        }
+        log::debug!("{} paths changed on disk", changes.len());
+        let _ = self
+            .reload_clickpacks()
+            .map_err(|e| log::error!("failed to reload clickpacks: {e}"));
+
+        let Some(path) = &self.clickpack_path else {
+            return;
+        };
+        if !self.conf.auto_reload_clickpack || self.is_loading_clickpack {
+            return;
+        }
+        let Ok(canonical) = path.canonicalize() else {
+            return;
+        };
+        if changes.iter().any(|p| p.starts_with(&canonical)) {
+            log::info!("{path:?} changed, reloading");
+            let path = path.clone();
+            let toast_queue = self.toast_queue.clone();
+            std::thread::spawn(move || {
+                Self::load_clickpack_thread(
+                    |e| {
+                        toast_queue.lock().unwrap().push(Toast {
+                            kind: ToastKind::Error,
+                            text: format!("Failed to reload clickpack: {e}").into(),
+                            options: ToastOptions::default().duration_in_seconds(5.0),
+                        })
+                    },
+                    &path,
+                )
+            });
+        }
+    }
+
+    /// Decoded frames of the current clickpack, see [SoundLoader::reuse].
+    fn reusable_sounds(&self) -> ReusableSounds {
+        let mut reuse = HashMap::new();
+        for sounds in [&self.players.0, &self.players.1] {
+            for (_, clicks) in sounds.categories() {
+                for click in clicks {
+                    if let Some(source) = &click.source {
+                        reuse.insert(source.clone(), (click.sample_rate(), click.frames.clone()));
+                    }
+                }
+            }
+        }
+        reuse
+    }
+
     pub fn draw_ui(&mut self, ctx: &egui::Context) {
         // process hotkeys
         let wants_keyboard = ctx.wants_keyboard_input();
@@ -1324,6 +2799,11 @@ impl Bot {
             self.conf.play_noise = !self.conf.play_noise;
             self.play_noise();
         }
+        self.update_noise_modulation();
+        self.apply_pending_clickpack();
+        self.apply_loaded_noise();
+        self.apply_loaded_layers();
+        self.play_simulation();
 
         // auto-save config
         if self.last_conf_save.elapsed() > Duration::from_secs(2)
@@ -1335,13 +2815,17 @@ impl Bot {
             self.prev_conf = self.conf.clone();
         }
 
+        // clickpacks are reloaded on changes even while the overlay is hidden
+        if self.watcher.is_some() {
+            self.handle_clickpack_changes();
+        }
+
         // don't draw/autosave if not open
         if self.conf.hidden {
             return;
         }
 
-        // clickpack reloading
-        if self.last_clickpack_reload.elapsed() > Duration::from_secs(3) {
+        if self.watcher.is_none() && self.last_clickpack_reload.elapsed() > Duration::from_secs(3) {
             let _ = self
                 .reload_clickpacks()
                 .map_err(|e| log::error!("failed to reload clickpacks: {e}"));
@@ -1560,7 +3044,7 @@ impl Bot {
                 {
                     let _ = std::fs::create_dir_all(".zcb")
                         .map_err(|e| log::error!("failed to create .zcb: {e}"));
-                    Command::new("explorer").arg(".zcb").spawn().unwrap();
+                    open_in_explorer(".zcb");
                 }
             });
             ui.label(format!(
@@ -1581,76 +3065,362 @@ impl Bot {
         }
     }
 
-    fn play_noise(&mut self) {
-        let stop_kittyaudio_noise = |noise_sound: &mut Option<SoundHandle>| {
-            if let Some(noise_sound) = noise_sound {
-                noise_sound.set_playback_rate(PlaybackRate::Factor(1.0));
-                noise_sound.set_loop_enabled(false);
-                noise_sound.seek_to_end();
-            }
-            *noise_sound = None;
-        };
-        let stop_fmod_noise = |fmodn: &mut *mut FMOD_CHANNEL| {
-            unsafe { FMOD_Channel_Stop(*fmodn) };
-            *fmodn = std::ptr::null_mut();
-        };
-        let mut start_kittyaudio_noise = |noise_sound: &mut Option<SoundHandle>| {
-            if let Some(mut noise) = self.noise.clone() {
-                noise.set_volume(self.conf.noise_volume);
-                noise.set_loop_enabled(true);
-                let frames = noise.frames.len().saturating_sub(1);
-                noise.set_loop_index(0..=frames);
-                noise.set_playback_rate(PlaybackRate::Factor(self.conf.noise_speedhack));
-                *noise_sound = Some(self.mixer.play(noise.sound));
-            }
-        };
-        let start_fmod_noise = |fmodn: &mut *mut FMOD_CHANNEL| unsafe {
-            if let Some(noise) = self.noise.clone() {
-                // get sound length
-                // let mut length = 0u32;
-                // FMOD_Sound_GetLength(noise.fmod_sound, &mut length, FMOD_TIMEUNIT_PCM);
+    fn stop_noise(&mut self) {
+        for voice in self.noise_voices.drain(..) {
+            voice.stop();
+        }
+    }
 
-                // set loop points for sound
+    fn start_noise_voice(&mut self, noise: SoundWrapper, volume: f32, speed: f64) -> NoiseVoice {
+        let gain = self.noise_gain;
+        let handle = if self.conf.use_fmod {
+            let mut channel = std::ptr::null_mut();
+            unsafe {
                 FMOD_Sound_SetLoopCount(noise.fmod_sound, i32::MAX);
-                // FMOD_Sound_SetLoopPoints(
-                //     noise.fmod_sound,
-                //     0,
-                //     FMOD_TIMEUNIT_PCM,
-                //     length,
-                //     FMOD_TIMEUNIT_PCM,
-                // );
-
-                // play the sound
                 FMOD_System_PlaySound(
                     self.system,
                     noise.fmod_sound,
                     std::ptr::null_mut(),
                     0,
-                    fmodn,
+                    &mut channel,
                 );
-
-                // update channel
-                FMOD_Channel_SetVolume(*fmodn, self.conf.noise_volume);
-                // FMOD_Channel_SetLoopPoints(*fmodn, 0, FMOD_TIMEUNIT_PCM, 1024, FMOD_TIMEUNIT_PCM);
-                FMOD_Channel_SetLoopCount(*fmodn, i32::MAX);
-                FMOD_Channel_SetPitch(*fmodn, self.conf.noise_speedhack as f32);
+                FMOD_Channel_SetVolume(channel, volume * gain);
+                FMOD_Channel_SetLoopCount(channel, i32::MAX);
+                FMOD_Channel_SetPitch(channel, speed as f32);
                 FMOD_System_Update(self.system);
             }
+            NoiseHandle::Fmod(channel)
+        } else {
+            let mut sound = noise.sound;
+            sound.set_volume(volume * gain);
+            sound.set_loop_enabled(true);
+            let frames = sound.frames.len().saturating_sub(1);
+            sound.set_loop_index(0..=frames);
+            sound.set_playback_rate(PlaybackRate::Factor(speed));
+            NoiseHandle::Kittyaudio(self.mixer.play(sound))
         };
+        NoiseVoice { handle, volume }
+    }
 
-        stop_kittyaudio_noise(&mut self.noise_sound);
-        stop_fmod_noise(&mut self.fmod_noise_sound);
+    /// Follow the clicking activity and change the noise volume with it.
+    fn update_noise_modulation(&mut self) {
+        let modulation = &self.conf.noise_modulation;
+        let level = self.noise_envelope.update(modulation, Instant::now());
+        let gain = modulation.gain(level);
+        // don't touch the channels every frame while nothing changes
+        if (gain - self.noise_gain).abs() < 1e-4 {
+            return;
+        }
+        self.noise_gain = gain;
+        for voice in &self.noise_voices {
+            voice.set_gain(gain);
+        }
+    }
 
-        if self.conf.use_fmod {
-            if self.conf.play_noise {
-                start_fmod_noise(&mut self.fmod_noise_sound);
+    /// Restart the clickpack noise and the noise layers with the current settings.
+    fn play_noise(&mut self) {
+        self.stop_noise();
+        if !self.conf.play_noise {
+            return;
+        }
+
+        let (volume, speed) = (self.conf.noise_volume, self.conf.noise_speedhack);
+        let mut noises = vec![];
+        if self.conf.use_clickpack_noise {
+            noises.extend(self.noise.clone().map(|noise| (noise, volume, speed)));
+        }
+        noises.extend(
+            self.generated_noise
+                .clone()
+                .map(|noise| (noise, volume, speed)),
+        );
+        for layer in self.conf.noise_layers.iter().filter(|l| l.enabled) {
+            if let Some(noise) = self.library_noise.get(&layer.file) {
+                noises.push((noise.clone(), volume * layer.volume, speed * layer.speed));
+            }
+        }
+        for (noise, volume, speed) in noises {
+            let voice = self.start_noise_voice(noise, volume, speed);
+            self.noise_voices.push(voice);
+        }
+    }
+
+    /// Generate the built-in noise again with the current settings.
+    fn regenerate_noise(&mut self) {
+        self.stop_noise();
+        if let Some(mut noise) = self.generated_noise.take() {
+            noise.free();
+        }
+        if self.conf.noise_generator.enabled {
+            let sound = self.conf.noise_generator.generate_loop();
+            self.generated_noise = Some(SoundWrapper::from_decoded(
+                &SoundLoader::new(self.system),
+                sound,
+                Path::new("generated noise"),
+            ));
+        }
+        self.play_noise();
+    }
+
+    /// Ask where to save the generated noise and write it as a WAV file.
+    fn export_generated_noise(&self) {
+        let Some(noise) = &self.generated_noise else {
+            return;
+        };
+        let (frames, sample_rate) = (noise.frames.clone(), noise.sample_rate());
+        let toast_queue = self.toast_queue.clone();
+        std::thread::spawn(move || {
+            let Some(path) = FileDialog::new()
+                .add_filter("WAV", &["wav"])
+                .set_file_name("noise.wav")
+                .save_file()
+            else {
+                return;
+            };
+            let result = std::fs::File::create(&path)
+                .and_then(|f| dsp::write_wav(std::io::BufWriter::new(f), &frames, sample_rate));
+            let toast = match result {
+                Ok(()) => Toast {
+                    kind: ToastKind::Success,
+                    text: format!("Saved noise to {}", path.display()).into(),
+                    options: ToastOptions::default().duration_in_seconds(3.0),
+                },
+                Err(e) => {
+                    log::error!("failed to export noise to {path:?}: {e}");
+                    Toast {
+                        kind: ToastKind::Error,
+                        text: format!("Failed to export noise: {e}").into(),
+                        options: ToastOptions::default().duration_in_seconds(5.0),
+                    }
+                }
+            };
+            toast_queue.lock().unwrap().push(toast);
+        });
+    }
+
+    fn show_noise_generator(&mut self, ui: &mut egui::Ui) {
+        ui.label("Built-in noise that works with any clickpack");
+        let gen = &mut self.conf.noise_generator;
+        let mut changed = ui.checkbox(&mut gen.enabled, "Enable generator").changed();
+        ui.add_enabled_ui(gen.enabled, |ui| {
+            egui::ComboBox::from_label("Color")
+                .selected_text(gen.color.name())
+                .show_ui(ui, |ui| {
+                    for color in NoiseColor::ALL {
+                        changed |= ui
+                            .selectable_value(&mut gen.color, color, color.name())
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text("White is bright and hissy, brown is deep and rumbly");
+            changed |= drag_value(
+                ui,
+                &mut gen.level,
+                "Level (dBFS)",
+                -100.0..=0.0,
+                "RMS level of the noise, before the noise volume",
+            )
+            .drag_released();
+            changed |= ui
+                .checkbox(&mut gen.lowpass, "Low-pass filter")
+                .on_hover_text("Remove high frequencies")
+                .changed();
+            ui.add_enabled_ui(gen.lowpass, |ui| {
+                changed |= drag_value(
+                    ui,
+                    &mut gen.cutoff,
+                    "Cutoff (Hz)",
+                    20.0..=20000.0,
+                    "Frequencies above this are removed",
+                )
+                .drag_released();
+            });
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.conf.noise_generator.enabled,
+                    egui::Button::new("Regenerate"),
+                )
+                .on_hover_text("Generate the noise again")
+                .clicked()
+            {
+                changed = true;
+            }
+            if ui
+                .add_enabled(
+                    self.generated_noise.is_some(),
+                    egui::Button::new("Export WAV"),
+                )
+                .on_hover_text("Save the generated noise as a .wav file")
+                .clicked()
+            {
+                self.export_generated_noise();
+            }
+        });
+        if changed {
+            self.regenerate_noise();
+        }
+    }
+
+    fn refresh_noise_library(&mut self) {
+        self.noise_library = noise::noise_library()
+            .map_err(|e| log::error!("failed to read {NOISE_LIBRARY_DIR}: {e}"))
+            .unwrap_or_default();
+    }
+
+    /// Load the noise layer files that aren't loaded yet on another thread.
+    /// They're added by [Bot::apply_loaded_noise].
+    fn load_noise_layers(&mut self) {
+        let mut files: Vec<PathBuf> = self
+            .conf
+            .noise_layers
+            .iter()
+            .filter(|l| !self.library_noise.contains_key(&l.file))
+            .map(|l| l.file.clone())
+            .collect();
+        // several layers can use the same file
+        files.sort();
+        files.dedup();
+        if files.is_empty() {
+            return;
+        }
+        let noise_loop = self.conf.noise_loop.clone();
+        let cache = self.pcm_cache();
+        let resample = self.conf.use_fmod;
+        std::thread::spawn(move || {
+            let _lock = NOISE_LOCK.lock().unwrap();
+            let loader = SoundLoader::new(unsafe { BOT.system })
+                .with_cache(cache)
+                .with_resampling(resample);
+            for file in files {
+                let path = Path::new(NOISE_LIBRARY_DIR).join(&file);
+                match load_noise(&ClickpackFs::Dir, &path, &loader, &noise_loop) {
+                    Ok(noise) => unsafe { BOT.loaded_noise.lock().unwrap().push((file, noise)) },
+                    Err(e) => log::error!("failed to load noise {path:?}: {e}"),
+                }
+            }
+        });
+    }
+
+    /// Add the noise layer files that finished loading and restart the noise.
+    /// Called on the game thread, so no noise is playing from a file while it's
+    /// replaced.
+    fn apply_loaded_noise(&mut self) {
+        let loaded = std::mem::take(&mut *self.loaded_noise.lock().unwrap());
+        if loaded.is_empty() {
+            return;
+        }
+        self.stop_noise();
+        for (file, mut noise) in loaded {
+            // the layer can be removed while its file is loading
+            if !self.conf.noise_layers.iter().any(|l| l.file == file) {
+                noise.free();
+                continue;
+            }
+            // a newer load replaces an older one, e.g. after reloading the layers
+            if let Some(mut old) = self.library_noise.insert(file, noise) {
+                old.free();
+            }
+        }
+        self.play_noise();
+    }
+
+    /// Release noise library files that no layer uses anymore. Must be called
+    /// while the noise is stopped.
+    fn free_unused_noise(&mut self) {
+        let layers = &self.conf.noise_layers;
+        self.library_noise.retain(|file, noise| {
+            let used = layers.iter().any(|l| &l.file == file);
+            if !used {
+                noise.free();
+            }
+            used
+        });
+    }
+
+    /// Load every noise layer again, e.g. to apply loop settings.
+    fn reload_noise_layers(&mut self) {
+        self.stop_noise();
+        for noise in self.library_noise.values_mut() {
+            noise.free();
+        }
+        self.library_noise.clear();
+        self.play_noise();
+        self.load_noise_layers();
+    }
+
+    fn show_noise_layers(&mut self, ui: &mut egui::Ui) {
+        ui.label("Noise from .zcb/noise that plays along with any clickpack");
+        let mut changed = false;
+        let mut remove = None;
+        for (i, layer) in self.conf.noise_layers.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(&mut layer.enabled, layer.file.to_string_lossy())
+                        .changed();
+                    if !self.library_noise.contains_key(&layer.file) {
+                        ui.spinner().on_hover_text("Not loaded");
+                    }
+                    if ui.button("🗙").on_hover_text("Remove layer").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    changed |= drag_value(ui, &mut layer.volume, "Volume", 0.0..=f32::INFINITY, "")
+                        .drag_released();
+                    changed |= drag_value(ui, &mut layer.speed, "Speed", 0.0..=f64::INFINITY, "")
+                        .drag_released();
+                });
+            });
+        }
+
+        let mut added = None;
+        ui.horizontal(|ui| {
+            if self.noise_library.is_empty() {
+                ui.label("Put noise files in .zcb/noise");
             } else {
-                stop_fmod_noise(&mut self.fmod_noise_sound);
+                egui::ComboBox::from_id_source("add_noise_layer")
+                    .selected_text("Add noise")
+                    .show_ui(ui, |ui| {
+                        for file in &self.noise_library {
+                            if ui.selectable_label(false, file.to_string_lossy()).clicked() {
+                                added = Some(file.clone());
+                            }
+                        }
+                    });
             }
-        } else if self.conf.play_noise {
-            start_kittyaudio_noise(&mut self.noise_sound);
-        } else {
-            stop_kittyaudio_noise(&mut self.noise_sound);
+            if ui
+                .button("🔄")
+                .on_hover_text("Refresh the noise library")
+                .clicked()
+            {
+                self.refresh_noise_library();
+            }
+            if ui
+                .button("Open folder")
+                .on_hover_text("Open .zcb/noise")
+                .clicked()
+            {
+                let _ = std::fs::create_dir_all(NOISE_LIBRARY_DIR)
+                    .map_err(|e| log::error!("failed to create {NOISE_LIBRARY_DIR}: {e}"));
+                open_in_explorer(".zcb\\noise");
+            }
+        });
+
+        if let Some(i) = remove {
+            self.conf.noise_layers.remove(i);
+            self.stop_noise();
+            self.free_unused_noise();
+            self.play_noise();
+        } else if changed {
+            self.play_noise();
+        }
+        if let Some(file) = added {
+            self.conf.noise_layers.push(NoiseLayer::new(file));
+            self.load_noise_layers();
         }
     }
 
@@ -1717,12 +3487,97 @@ impl Bot {
         });
     }
 
+    /// Queue the simulator pattern, it's played by [Bot::play_simulation].
+    fn start_simulation(&mut self) {
+        let start = Instant::now();
+        self.simulation = self
+            .conf
+            .simulator
+            .actions()
+            .into_iter()
+            .map(|action| (start + Duration::from_secs_f64(action.time), action))
+            .collect();
+        // the pattern starts after an idle period, like the first click of a level
+        self.prev_time = 0.0;
+    }
+
+    /// Play the simulator actions that are due. Called every frame on the game
+    /// thread, like the actions of a level. Click types are picked from the
+    /// times of the pattern, so they don't depend on the frame rate.
+    fn play_simulation(&mut self) {
+        let now = Instant::now();
+        while let Some(&(at, action)) = self.simulation.front() {
+            if at > now {
+                break;
+            }
+            self.simulation.pop_front();
+            self.play_action(action.push, action.player2, Button::Jump, action.time);
+        }
+    }
+
+    fn stop_simulation(&mut self) {
+        self.simulation.clear();
+    }
+
+    fn show_simulator(&mut self, ui: &mut egui::Ui) {
+        ui.label("Play click patterns without a level to try out the settings");
+        let sim = &mut self.conf.simulator;
+        egui::ComboBox::from_label("Pattern")
+            .selected_text(sim.pattern.name())
+            .show_ui(ui, |ui| {
+                for pattern in Pattern::ALL {
+                    ui.selectable_value(&mut sim.pattern, pattern, pattern.name());
+                }
+            });
+        match sim.pattern {
+            Pattern::Tap => {}
+            Pattern::Spam | Pattern::DualSpam => {
+                drag_value(
+                    ui,
+                    &mut sim.cps,
+                    "Clicks per second",
+                    0.1..=100.0,
+                    "How fast to spam",
+                );
+            }
+            Pattern::Hold => {
+                drag_value(
+                    ui,
+                    &mut sim.hold,
+                    "Hold length (ms)",
+                    0.0..=5000.0,
+                    "How long every click is held, the pause in between is as long",
+                );
+            }
+        }
+        drag_value(ui, &mut sim.count, "Clicks", 1..=1000, "");
+
+        let can_play = self.num_sounds != (0, 0) && self.playlayer.is_null();
+        if !self.simulation.is_empty() {
+            if ui.button("Stop").clicked() {
+                self.stop_simulation();
+            }
+        } else if ui
+            .add_enabled(can_play, egui::Button::new("Play pattern"))
+            .on_disabled_hover_text("Needs a clickpack and can't be used while playing a level")
+            .clicked()
+        {
+            self.start_simulation();
+        }
+    }
+
     fn show_audio_window(&mut self, ui: &mut egui::Ui, toasts: &mut Toasts) {
-        ui.add_enabled_ui(self.noise.is_some() && !self.is_loading_clickpack, |ui| {
+        let has_noise = self.noise.is_some()
+            || !self.conf.noise_layers.is_empty()
+            || self.conf.noise_generator.enabled;
+        ui.add_enabled_ui(has_noise && !self.is_loading_clickpack, |ui| {
             ui.horizontal(|ui| {
                 if ui
                     .checkbox(&mut self.conf.play_noise, "Play noise")
-                    .on_disabled_hover_text("Your clickpack doesn't have a noise file")
+                    .on_disabled_hover_text(
+                        "Your clickpack doesn't have a noise file, add a noise layer\n\
+                        or enable the noise generator",
+                    )
                     .on_hover_text("Play the noise file")
                     .changed()
                 {
@@ -1743,6 +3598,58 @@ impl Bot {
                 }
             });
         });
+        ui.add_enabled_ui(self.noise.is_some(), |ui| {
+            if ui
+                .checkbox(&mut self.conf.use_clickpack_noise, "Clickpack noise")
+                .on_hover_text("Play the noise file of the clickpack")
+                .on_disabled_hover_text("Your clickpack doesn't have a noise file")
+                .changed()
+            {
+                self.play_noise();
+            }
+        });
+        ui.collapsing("Noise layers", |ui| self.show_noise_layers(ui));
+        ui.collapsing("Noise generator", |ui| self.show_noise_generator(ui));
+        ui.collapsing("Noise modulation", |ui| {
+            ui.label("Change the noise volume with how much you're clicking");
+            let modulation = &mut self.conf.noise_modulation;
+            ui.checkbox(&mut modulation.enabled, "Enable modulation");
+            ui.add_enabled_ui(modulation.enabled, |ui| {
+                egui::ComboBox::from_label("Mode")
+                    .selected_text(modulation.mode.name())
+                    .show_ui(ui, |ui| {
+                        for mode in NoiseModulationMode::ALL {
+                            ui.selectable_value(&mut modulation.mode, mode, mode.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Duck makes the noise quieter while clicking,\n\
+                        swell makes it louder",
+                    );
+                drag_value(
+                    ui,
+                    &mut modulation.attack,
+                    "Attack (ms)",
+                    0.0..=5000.0,
+                    "How fast the noise reacts to clicking",
+                );
+                drag_value(
+                    ui,
+                    &mut modulation.release,
+                    "Release (ms)",
+                    0.0..=10000.0,
+                    "How fast the noise goes back after you stop clicking",
+                );
+                drag_value(
+                    ui,
+                    &mut modulation.depth,
+                    "Depth (dB)",
+                    0.0..=60.0,
+                    "Volume difference between idle and clicking",
+                );
+            });
+        });
 
         help_text(
             ui,
@@ -1755,7 +3662,11 @@ impl Bot {
                     } else {
                         self.maybe_init_kittyaudio();
                     }
-                    self.play_noise();
+                    // sounds are only resampled for fmod, see [SoundLoader::resample]
+                    if let Some(path) = self.clickpack_path.clone() {
+                        self.reload_clickpack(path);
+                    }
+                    self.reload_noise_layers();
                 }
             },
         );
@@ -1767,6 +3678,13 @@ impl Bot {
 
         ui.separator();
 
+        if self.active_manifest().is_some_and(|m| m.has_overrides()) {
+            ui.label(
+                RichText::new("The loaded clickpack overrides some of these settings")
+                    .color(Color32::YELLOW),
+            );
+        }
+
         ui.collapsing("Timings", |ui| {
             let timings_copy = self.conf.timings.clone();
             let timings = &mut self.conf.timings;
@@ -1822,6 +3740,185 @@ impl Bot {
             });
         });
 
+        ui.collapsing("Sample selection", |ui| {
+            ui.label("How a sample is picked from a category");
+            egui::ComboBox::from_label("Selection mode")
+                .selected_text(self.conf.selection_mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in SelectionMode::ALL {
+                        ui.selectable_value(&mut self.conf.selection_mode, mode, mode.name())
+                            .on_hover_text(mode.description());
+                    }
+                });
+            if self.conf.selection_mode == SelectionMode::AvoidRecent {
+                drag_value(
+                    ui,
+                    &mut self.conf.avoid_last,
+                    "Avoid last N",
+                    1..=usize::MAX,
+                    "Number of recently played samples that won't be picked again",
+                );
+            }
+        });
+
+        ui.collapsing("Sample processing", |ui| {
+            ui.label("Applied to clicks when the clickpack is loaded");
+            let p = &mut self.conf.processing;
+            ui.checkbox(&mut p.enabled, "Enable processing");
+            ui.add_enabled_ui(p.enabled, |ui| {
+                help_text(
+                    ui,
+                    "Remove silence at the start of every sample.\n\
+                    This lowers the latency of clicks",
+                    |ui| ui.checkbox(&mut p.trim_silence, "Trim leading silence"),
+                );
+                ui.add_enabled_ui(p.trim_silence, |ui| {
+                    drag_value(
+                        ui,
+                        &mut p.silence_threshold,
+                        "Silence threshold (dB)",
+                        -120.0..=0.0,
+                        "Anything quieter than this is considered silence",
+                    );
+                });
+                drag_value(
+                    ui,
+                    &mut p.fade_in,
+                    "Fade-in (ms)",
+                    0.0..=100.0,
+                    "Short fade at the start of every sample to avoid clicks, 0 to disable",
+                );
+                drag_value(
+                    ui,
+                    &mut p.fade_out,
+                    "Fade-out (ms)",
+                    0.0..=100.0,
+                    "Short fade at the end of every sample to avoid clicks, 0 to disable",
+                );
+            });
+            self.show_reload_clickpack_button(ui);
+        });
+
+        ui.collapsing("Normalization", |ui| {
+            ui.label("Match the loudness of clickpacks when they're loaded");
+            let norm = &mut self.conf.normalization;
+            ui.checkbox(&mut norm.enabled, "Enable normalization");
+            ui.add_enabled_ui(norm.enabled, |ui| {
+                egui::ComboBox::from_label("Method")
+                    .selected_text(norm.method.name())
+                    .show_ui(ui, |ui| {
+                        for method in NormalizationMethod::ALL {
+                            if ui
+                                .selectable_value(&mut norm.method, method, method.name())
+                                .changed()
+                            {
+                                norm.target = method.default_target();
+                            }
+                        }
+                    });
+                egui::ComboBox::from_label("Scope")
+                    .selected_text(norm.scope.name())
+                    .show_ui(ui, |ui| {
+                        for scope in NormalizationScope::ALL {
+                            ui.selectable_value(&mut norm.scope, scope, scope.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Per category changes the balance between categories,\n\
+                        per clickpack keeps it",
+                    );
+                drag_value(
+                    ui,
+                    &mut norm.target,
+                    format!("Target level ({})", norm.method.unit()),
+                    -60.0..=0.0,
+                    "Level that the clickpack is normalized to",
+                );
+                drag_value(
+                    ui,
+                    &mut norm.max_gain,
+                    "Maximum gain (dB)",
+                    0.0..=60.0,
+                    "Limits how much quiet clickpacks are amplified",
+                );
+            });
+            self.show_reload_clickpack_button(ui);
+        });
+
+        ui.collapsing("Noise looping", |ui| {
+            let noise_loop = &mut self.conf.noise_loop;
+            help_text(
+                ui,
+                "Crossfade the end of the noise into its start.\n\
+                Removes the click when the noise loops",
+                |ui| ui.checkbox(&mut noise_loop.crossfade, "Crossfade loop"),
+            );
+            ui.add_enabled_ui(noise_loop.crossfade, |ui| {
+                drag_value(
+                    ui,
+                    &mut noise_loop.crossfade_ms,
+                    "Crossfade length (ms)",
+                    0.0..=5000.0,
+                    "Longer crossfades hide the loop better, but shorten the noise",
+                );
+                help_text(
+                    ui,
+                    "Search the end of the noise for the spot that matches its start best.\n\
+                    Cuts up to a second from the end",
+                    |ui| ui.checkbox(&mut noise_loop.auto_loop_point, "Find loop point"),
+                );
+            });
+            ui.horizontal(|ui| {
+                self.show_reload_clickpack_button(ui);
+                if !self.conf.noise_layers.is_empty()
+                    && ui
+                        .button("Reload noise layers")
+                        .on_hover_text("Load the noise layers again to apply changes")
+                        .clicked()
+                {
+                    self.reload_noise_layers();
+                }
+            });
+        });
+
+        ui.collapsing("Cache", |ui| {
+            help_text(
+                ui,
+                "Keep decoded sounds in .zcb/cache so clickpacks load faster.\n\
+                Uses up to the maximum size of disk space, off by default.\n\
+                Changed files are decoded again automatically",
+                |ui| ui.checkbox(&mut self.conf.cache.enabled, "Cache decoded sounds"),
+            );
+            ui.add_enabled_ui(self.conf.cache.enabled, |ui| {
+                drag_value(
+                    ui,
+                    &mut self.conf.cache.max_size,
+                    "Maximum size (MB)",
+                    1..=u32::MAX,
+                    "Least recently used sounds are removed when the cache gets bigger than this",
+                );
+            });
+            if ui
+                .button("Clear cache")
+                .on_hover_ui(|ui| {
+                    let size = PcmCache::default().size();
+                    ui.label(format!(
+                        "Cache size: {:.1} MB",
+                        size as f64 / (1024.0 * 1024.0)
+                    ));
+                })
+                .clicked()
+            {
+                let freed = PcmCache::default().clear();
+                toasts.add(Toast {
+                    kind: ToastKind::Success,
+                    text: format!("Freed {:.1} MB", freed as f64 / (1024.0 * 1024.0)).into(),
+                    options: ToastOptions::default().duration_in_seconds(2.0),
+                });
+            }
+        });
+
         ui.collapsing("Volume settings", |ui| {
             help_text(
                 ui,
@@ -1900,7 +3997,7 @@ impl Bot {
                     &mut self.conf.noise_speedhack,
                     "Noise speedhack",
                     0.0..=f64::INFINITY,
-                    "Speed multiplier for noise. Only useful if your clickpack has a noise file",
+                    "Speed multiplier for the clickpack noise and noise layers",
                 )
                 .drag_released()
                 {
@@ -1916,6 +4013,8 @@ impl Bot {
             // });
         });
 
+        ui.collapsing("Settings simulator", |ui| self.show_simulator(ui));
+
         ui.collapsing("Advanced", |ui| {
             // let last_bufsize = self.mixer.renderer.guard().last_buffer_size;
             // ui.label(format!("Real buffer size: {last_bufsize}"));
@@ -1978,15 +4077,428 @@ impl Bot {
         self.play_noise();
     }
 
-    fn load_clickpack_thread(err_fn: impl Fn(anyhow::Error), dir: &Path) {
-        unsafe { BOT.is_loading_clickpack = true };
-        if let Err(e) = unsafe { BOT.load_clickpack(dir) } {
-            log::error!("failed to load clickpack: {e}");
-            err_fn(e);
-        }
-        unsafe { BOT.is_loading_clickpack = false };
-    }
-
+    /// Button that loads the current clickpack again, e.g. to apply load-time settings.
+    fn show_reload_clickpack_button(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = self.clickpack_path.clone() else {
+            return;
+        };
+        if ui
+            .add_enabled(
+                !self.is_loading_clickpack,
+                egui::Button::new("Reload clickpack"),
+            )
+            .on_hover_text("Load the clickpack again to apply changes")
+            .clicked()
+        {
+            self.reload_clickpack(path);
+        }
+    }
+
+    /// Load the clickpack at `path` and the clickpack layers again.
+    fn reload_clickpack(&mut self, path: PathBuf) {
+        let toast_queue = self.toast_queue.clone();
+        std::thread::spawn(move || {
+            Self::load_clickpack_thread(
+                |e| {
+                    toast_queue.lock().unwrap().push(Toast {
+                        kind: ToastKind::Error,
+                        text: format!("Failed to reload clickpack: {e}").into(),
+                        options: ToastOptions::default().duration_in_seconds(5.0),
+                    })
+                },
+                &path,
+            )
+        });
+        self.reload_layers();
+    }
+
+    /// Paths of the clickpack layers in [Env::layers].
+    fn layer_paths(&self) -> Vec<PathBuf> {
+        self.env
+            .layers
+            .iter()
+            .filter_map(|layer| layer.clickpack.resolve(&self.clickpacks))
+            .collect()
+    }
+
+    /// Load the clickpack layers that aren't loaded yet on another thread.
+    /// They're added by [Bot::apply_loaded_layers].
+    fn load_layers(&mut self) {
+        let mut paths = self.layer_paths();
+        paths.retain(|path| !self.layer_sounds.contains_key(path));
+        paths.sort();
+        paths.dedup();
+        if paths.is_empty() {
+            return;
+        }
+        let conf = self.conf.clone();
+        let loudness_cache = self.loudness_cache.clone();
+        std::thread::spawn(move || {
+            let _lock = LAYER_LOCK.lock().unwrap();
+            let loader = unsafe { BOT.sound_loader() };
+            for path in paths {
+                match LoadedClickpack::load(&path, &loader, &conf, &loudness_cache, false) {
+                    Ok(clickpack) => unsafe {
+                        log::info!("loaded clickpack layer {path:?}");
+                        BOT.loaded_layers
+                            .lock()
+                            .unwrap()
+                            .push((path, clickpack.players));
+                    },
+                    Err(e) => unsafe {
+                        log::error!("failed to load clickpack layer {path:?}: {e}");
+                        BOT.toast_queue.lock().unwrap().push(Toast {
+                            kind: ToastKind::Error,
+                            text: format!("Failed to load clickpack layer: {e}").into(),
+                            options: ToastOptions::default().duration_in_seconds(5.0),
+                        });
+                    },
+                }
+            }
+        });
+    }
+
+    /// Add the clickpack layers that finished loading. Called on the game
+    /// thread, so the layers aren't changed while a click is picked from them.
+    fn apply_loaded_layers(&mut self) {
+        let loaded = std::mem::take(&mut *self.loaded_layers.lock().unwrap());
+        if loaded.is_empty() {
+            return;
+        }
+        self.stop_simulation();
+        let used = self.layer_paths();
+        for (path, (mut p1, mut p2)) in loaded {
+            // the layer can be removed while it's loading
+            if !used.contains(&path) {
+                p1.free_fmod_sounds();
+                p2.free_fmod_sounds();
+                continue;
+            }
+            // a newer load replaces an older one, e.g. after reloading the layers
+            let layer = LayerSounds {
+                players: (p1, p2),
+                weight: 1.0,
+                gain: 1.0,
+            };
+            if let Some(mut prev) = self.layer_sounds.insert(path, layer) {
+                prev.free_fmod_sounds();
+            }
+        }
+        self.update_layer_settings();
+    }
+
+    /// Copy the weight and gain of [Env::layers] to the loaded layers.
+    fn update_layer_settings(&mut self) {
+        for layer in &self.env.layers {
+            let Some(path) = layer.clickpack.resolve(&self.clickpacks) else {
+                continue;
+            };
+            if let Some(sounds) = self.layer_sounds.get_mut(&path) {
+                sounds.weight = layer.weight;
+                sounds.gain = layer.gain;
+            }
+        }
+    }
+
+    /// Release the layers that were removed from [Env::layers].
+    fn free_unused_layers(&mut self) {
+        self.stop_simulation();
+        let used = self.layer_paths();
+        self.layer_sounds.retain(|path, layer| {
+            let is_used = used.contains(path);
+            if !is_used {
+                layer.free_fmod_sounds();
+            }
+            is_used
+        });
+    }
+
+    /// Load every layer again, e.g. to apply load-time settings.
+    fn reload_layers(&mut self) {
+        if self.env.layers.is_empty() {
+            return;
+        }
+        self.stop_simulation();
+        for (_, mut layer) in self.layer_sounds.drain() {
+            layer.free_fmod_sounds();
+        }
+        self.load_layers();
+    }
+
+    fn show_player2_clickpack(&mut self, ui: &mut egui::Ui) {
+        let selected = match &self.env.player2_clickpack {
+            Clickpack::None => "Same as player 1".to_string(),
+            clickpack => clickpack.name(),
+        };
+        let mut changed = false;
+        egui::ComboBox::from_label("Player 2 clickpack")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                changed |= ui
+                    .selectable_value(
+                        &mut self.env.player2_clickpack,
+                        Clickpack::None,
+                        "Same as player 1",
+                    )
+                    .on_hover_text("Use the player 2 folder of the clickpack")
+                    .changed();
+                for path in &self.clickpacks {
+                    let clickpack =
+                        Clickpack::Name(path.file_name().unwrap().to_string_lossy().to_string());
+                    let name = clickpack.name();
+                    changed |= ui
+                        .selectable_value(&mut self.env.player2_clickpack, clickpack, name)
+                        .changed();
+                }
+            })
+            .response
+            .on_hover_text("Play a different clickpack for player 2 in dual mode");
+        if !changed {
+            return;
+        }
+        self.env.save();
+
+        // load the new player 2 sounds along with the main clickpack
+        let Some(path) = self.clickpack_path.clone() else {
+            return;
+        };
+        let toast_queue = self.toast_queue.clone();
+        std::thread::spawn(move || {
+            Self::load_clickpack_thread(
+                |e| {
+                    toast_queue.lock().unwrap().push(Toast {
+                        kind: ToastKind::Error,
+                        text: format!("Failed to load clickpack: {e:#}").into(),
+                        options: ToastOptions::default().duration_in_seconds(5.0),
+                    })
+                },
+                &path,
+            )
+        });
+    }
+
+    fn show_clickpack_layers(&mut self, ui: &mut egui::Ui) {
+        ui.label("Play other clickpacks along with this one");
+        let mut changed = false;
+        egui::ComboBox::from_label("Layer mode")
+            .selected_text(self.env.layer_mode.name())
+            .show_ui(ui, |ui| {
+                for mode in LayerMode::ALL {
+                    changed |= ui
+                        .selectable_value(&mut self.env.layer_mode, mode, mode.name())
+                        .on_hover_text(mode.description())
+                        .changed();
+                }
+            })
+            .response
+            .on_hover_text(self.env.layer_mode.description());
+
+        // the loaded layers get new values right away, the env is saved when
+        // the drag ends
+        let mut edited = false;
+        let mut weight_and_gain = |ui: &mut egui::Ui, weight: &mut f32, gain: &mut f32| {
+            ui.horizontal(|ui| {
+                for (value, text) in [(weight, "Weight"), (gain, "Gain")] {
+                    let resp = drag_value(ui, value, text, 0.0..=f32::INFINITY, "");
+                    edited |= resp.changed();
+                    changed |= resp.drag_released();
+                }
+            });
+        };
+        ui.label(format!("Main: {}", self.selected_clickpack));
+        weight_and_gain(
+            ui,
+            &mut self.env.clickpack_weight,
+            &mut self.env.clickpack_gain,
+        );
+
+        let (mut remove, mut swap) = (None, None);
+        let num_layers = self.env.layers.len();
+        for (i, layer) in self.env.layers.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}. {}", i + 1, layer.clickpack.name()));
+                    let is_loaded = layer
+                        .clickpack
+                        .resolve(&self.clickpacks)
+                        .is_some_and(|path| self.layer_sounds.contains_key(&path));
+                    if !is_loaded {
+                        ui.spinner().on_hover_text("Not loaded");
+                    }
+                    ui.style_mut().spacing.item_spacing.x = 4.0;
+                    if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                        swap = Some((i, i - 1));
+                    }
+                    if ui
+                        .add_enabled(i + 1 < num_layers, egui::Button::new("⏷"))
+                        .clicked()
+                    {
+                        swap = Some((i, i + 1));
+                    }
+                    if ui.button("🗙").on_hover_text("Remove layer").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                weight_and_gain(ui, &mut layer.weight, &mut layer.gain);
+            });
+        }
+
+        let mut added = None;
+        let main = self.clickpack_path.clone();
+        egui::ComboBox::from_id_source("add_clickpack_layer")
+            .selected_text("Add layer")
+            .show_ui(ui, |ui| {
+                for path in &self.clickpacks {
+                    let clickpack =
+                        Clickpack::Name(path.file_name().unwrap().to_string_lossy().to_string());
+                    let is_used = main.as_ref() == Some(path)
+                        || self.env.layers.iter().any(|l| l.clickpack == clickpack);
+                    if ui
+                        .add_enabled(
+                            !is_used,
+                            egui::SelectableLabel::new(false, clickpack.name()),
+                        )
+                        .clicked()
+                    {
+                        added = Some(clickpack);
+                    }
+                }
+            });
+
+        if let Some((a, b)) = swap {
+            self.env.layers.swap(a, b);
+            changed = true;
+        }
+        if let Some(i) = remove {
+            self.env.layers.remove(i);
+            self.free_unused_layers();
+            changed = true;
+        }
+        if let Some(clickpack) = added {
+            self.env.layers.push(ClickpackLayer::new(clickpack));
+            self.load_layers();
+            changed = true;
+        }
+        if edited {
+            self.update_layer_settings();
+        }
+        if changed {
+            self.env.save();
+        }
+    }
+
+    fn load_clickpack_thread(err_fn: impl Fn(anyhow::Error), dir: &Path) {
+        // picking another clickpack cancels the one that is still loading
+        let progress = Arc::new(LoadProgress::default());
+        if let Some(prev) = unsafe { BOT.load_progress.replace(progress.clone()) } {
+            prev.cancel();
+        }
+        unsafe { BOT.is_loading_clickpack = true };
+
+        // wait for the cancelled load to stop
+        let lock = LOAD_LOCK.lock().unwrap();
+        if !progress.is_cancelled() {
+            // the current clickpack keeps playing until the new one is ready, and
+            // stays loaded if this one fails
+            let (loader, conf, loudness_cache, player2) = unsafe {
+                let mut loader = BOT.sound_loader().with_progress(progress.clone());
+                // reloading the same clickpack only decodes the files that changed
+                if BOT.clickpack_path.as_deref() == Some(dir) {
+                    loader = loader.with_reuse(BOT.reusable_sounds());
+                }
+                let player2 = BOT
+                    .env
+                    .player2_clickpack
+                    .resolve(&BOT.clickpacks)
+                    .filter(|path| path != dir);
+                (
+                    loader,
+                    BOT.conf.clone(),
+                    BOT.loudness_cache.clone(),
+                    player2,
+                )
+            };
+            let result = LoadedClickpack::load(dir, &loader, &conf, &loudness_cache, true).map(
+                |mut clickpack| {
+                    let Some(player2) = player2 else {
+                        return clickpack;
+                    };
+                    match LoadedClickpack::load(&player2, &loader, &conf, &loudness_cache, false) {
+                        Ok(other) => clickpack.use_player2_from(other),
+                        Err(_) if progress.is_cancelled() => {}
+                        Err(e) => {
+                            // keep the player 2 sounds of the clickpack itself
+                            log::error!("failed to load player 2 clickpack {player2:?}: {e}");
+                            err_fn(e.context("failed to load the player 2 clickpack"));
+                        }
+                    }
+                    clickpack
+                },
+            );
+            match result {
+                Ok(mut clickpack) if progress.is_cancelled() => clickpack.free(),
+                Ok(clickpack) => unsafe {
+                    // a clickpack that was never swapped in was never played
+                    let prev = BOT.pending_clickpack.lock().unwrap().replace(clickpack);
+                    if let Some(mut prev) = prev {
+                        prev.free();
+                    }
+                    if let Some(cache) = BOT.pcm_cache() {
+                        cache.trim(BOT.conf.cache.max_bytes());
+                    }
+                },
+                Err(_) if progress.is_cancelled() => {
+                    log::info!("cancelled loading clickpack {dir:?}");
+                }
+                Err(e) => {
+                    log::error!("failed to load clickpack: {e}");
+                    err_fn(e);
+                }
+            }
+        }
+        drop(lock);
+
+        // don't touch the state if a newer load has started
+        unsafe {
+            if BOT
+                .load_progress
+                .as_ref()
+                .is_some_and(|p| Arc::ptr_eq(p, &progress))
+            {
+                BOT.load_progress = None;
+                BOT.is_loading_clickpack = false;
+            }
+        }
+    }
+
+    fn show_load_progress(&mut self, ui: &mut egui::Ui) {
+        let Some(progress) = self.load_progress.clone() else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            let (done, total) = progress.files();
+            if total == 0 {
+                ui.label("Loading clickpack...");
+                ui.add(egui::Spinner::new());
+            } else {
+                ui.add(
+                    egui::ProgressBar::new(done as f32 / total as f32)
+                        .text(format!("Loading clickpack... {done}/{total}"))
+                        .desired_width(250.0),
+                );
+            }
+            if ui
+                .add_enabled(!progress.is_cancelled(), egui::Button::new("Cancel"))
+                .clicked()
+            {
+                progress.cancel();
+            }
+        });
+        if let Some(filename) = progress.current_file().file_name() {
+            ui.label(RichText::new(filename.to_string_lossy()).small().weak());
+        }
+    }
+
     fn select_clickpack_combobox(&mut self, ui: &mut egui::Ui, modal: Arc<Mutex<Modal>>) {
         let ellipsis = if self.selected_clickpack.len() <= 14 {
             self.selected_clickpack.clone()
@@ -2032,13 +4544,30 @@ impl Bot {
             return true;
         }
         ui.horizontal(|ui| {
+            let mut pick_zip = None;
             if ui
                 .button("Select clickpack")
                 .on_disabled_hover_text("Please wait...")
                 .clicked()
             {
+                pick_zip = Some(false);
+            }
+            if ui
+                .button("Select .zip")
+                .on_hover_text("Load a clickpack from a zip archive")
+                .clicked()
+            {
+                pick_zip = Some(true);
+            }
+            if let Some(pick_zip) = pick_zip {
                 std::thread::spawn(move || {
-                    let Some(dir) = FileDialog::new().pick_folder() else {
+                    let dialog = FileDialog::new();
+                    let dir = if pick_zip {
+                        dialog.add_filter("Zip archive", &["zip"]).pick_file()
+                    } else {
+                        dialog.pick_folder()
+                    };
+                    let Some(dir) = dir else {
                         return;
                     };
                     log::debug!("selected clickpack {:?}", dir);
@@ -2072,59 +4601,523 @@ impl Bot {
         false
     }
 
-    fn show_clickpack_window(&mut self, ui: &mut egui::Ui, modal: Arc<Mutex<Modal>>) {
-        if self.is_loading_clickpack {
-            ui.horizontal(|ui| {
-                ui.label("Loading clickpack...");
+    fn show_manifest_info(&mut self, ui: &mut egui::Ui) {
+        let Some(manifest) = &self.manifest else {
+            return;
+        };
+        if let Some(author) = &manifest.author {
+            ui.label(format!("Author: {author}"));
+        }
+        if let Some(version) = &manifest.version {
+            ui.label(format!("Version: {version}"));
+        }
+        if let Some(description) = &manifest.description {
+            ui.label(RichText::new(description).italics());
+        }
+        if manifest.has_overrides() {
+            help_text(
+                ui,
+                "This clickpack overrides some of your timings, pitch\n\
+                or volume settings in its clickpack.json",
+                |ui| {
+                    ui.checkbox(
+                        &mut self.conf.use_clickpack_settings,
+                        "Use clickpack settings",
+                    )
+                },
+            );
+        }
+    }
+
+    fn show_clickpack_report(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = self.clickpack_path.clone() else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !self.is_analyzing_clickpack && !self.is_loading_clickpack,
+                    egui::Button::new("Check clickpack"),
+                )
+                .on_hover_text("Look for missing categories, broken files and other problems")
+                .clicked()
+            {
+                self.is_analyzing_clickpack = true;
+                let toast_queue = self.toast_queue.clone();
+                let rules = self.conf.folder_rules.clone();
+                std::thread::spawn(move || {
+                    match lint::analyze(&path, &rules) {
+                        Ok(report) => unsafe { BOT.clickpack_report = Some(report) },
+                        Err(e) => {
+                            log::error!("failed to analyze clickpack: {e}");
+                            toast_queue.lock().unwrap().push(Toast {
+                                kind: ToastKind::Error,
+                                text: format!("Failed to analyze clickpack: {e}").into(),
+                                options: ToastOptions::default().duration_in_seconds(5.0),
+                            });
+                        }
+                    }
+                    unsafe { BOT.is_analyzing_clickpack = false };
+                });
+            }
+            if self.is_analyzing_clickpack {
                 ui.add(egui::Spinner::new());
+            }
+        });
+
+        let Some(report) = &self.clickpack_report else {
+            return;
+        };
+        let title = format!(
+            "Report: {} errors, {} warnings, {} notes",
+            report.count(Severity::Error),
+            report.count(Severity::Warning),
+            report.count(Severity::Info)
+        );
+        ui.collapsing(title, |ui| {
+            ui.label(format!(
+                "{} player 1 sounds, {} player 2 sounds",
+                report.num_sounds.0, report.num_sounds.1
+            ));
+            let (bank1, bank2) = report.num_bank_sounds;
+            if bank1 + bank2 > 0 {
+                ui.label(format!(
+                    "{bank1} player 1 sounds, {bank2} player 2 sounds in button folders"
+                ));
+            }
+            match &report.noise {
+                Some(noise) => ui.label(format!("Noise: {}", noise.display())),
+                None => ui.label("No noise file"),
+            };
+            if report.issues.is_empty() {
+                ui.label("No problems found");
+            }
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for issue in &report.issues {
+                        let color = match issue.severity() {
+                            Severity::Error => Color32::LIGHT_RED,
+                            Severity::Warning => Color32::YELLOW,
+                            Severity::Info => ui.visuals().text_color(),
+                        };
+                        ui.label(RichText::new(issue.message()).color(color));
+                    }
+                });
+        });
+    }
+
+    /// Which folders the sounds of the current clickpack were loaded from.
+    fn show_folders(&mut self, ui: &mut egui::Ui) {
+        let root = self.clickpack_path.clone().unwrap_or_default();
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for folder in &self.folders {
+                    // zip paths are already relative to the archive
+                    let path = folder.path.strip_prefix(&root).unwrap_or(&folder.path);
+                    let path = if path.as_os_str().is_empty() {
+                        ".".to_string()
+                    } else {
+                        path.display().to_string()
+                    };
+                    let player = if folder.player2 { 2 } else { 1 };
+                    let bank = folder
+                        .button
+                        .map(|button| format!(" ({} button)", button.dirname()))
+                        .unwrap_or_default();
+                    let matched_by = match folder.matched_by {
+                        MatchedBy::Folder => "",
+                        MatchedBy::FileName => " by file name",
+                        MatchedBy::Nothing => " (unknown file names)",
+                        MatchedBy::Loudness => " by loudness",
+                    };
+                    ui.label(format!(
+                        "{path} → player {player}, {:?}{bank}{matched_by}, {} files",
+                        folder.typ,
+                        folder.files.len()
+                    ));
+                }
             });
+    }
+
+    fn show_folder_rules(&mut self, ui: &mut egui::Ui) {
+        help_text(
+            ui,
+            "Case, spaces, _ and - are ignored.\n\
+            * matches anything, ? matches a single character.\n\
+            Folders that match nothing are not loaded",
+            |ui| {
+                ui.label("Folder and file name patterns");
+            },
+        );
+
+        let show_patterns = |ui: &mut egui::Ui, name: &str, patterns: &mut Vec<String>| {
+            ui.collapsing(name, |ui| {
+                let mut remove = None;
+                for (i, pattern) in patterns.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(pattern);
+                        if ui.button("🗙").on_hover_text("Remove pattern").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    patterns.remove(i);
+                }
+                if ui.button("Add pattern").clicked() {
+                    patterns.push(String::new());
+                }
+            });
+        };
+        let rules = &mut self.conf.folder_rules;
+        show_patterns(ui, "Player 1", &mut rules.player1);
+        show_patterns(ui, "Player 2", &mut rules.player2);
+        for (typ, patterns) in &mut rules.categories {
+            show_patterns(ui, &format!("{typ:?}"), patterns);
         }
-        let has_sounds = self.num_sounds != (0, 0);
+        ui.collapsing("File names", |ui| {
+            ui.label("Used when there's no category folders");
+            for (typ, patterns) in &mut rules.files {
+                show_patterns(ui, &format!("{typ:?}"), patterns);
+            }
+        });
 
-        ui.add_enabled_ui(!self.is_loading_clickpack, |ui| {
-            if !self.clickpacks.is_empty() {
-                help_text(
-                    ui,
-                    "If there's no folders inside .zcb/clickpacks,\n\
-                    there will be an option to choose the clickpack manually",
-                    |ui| {
-                        ui.label("Put clickpacks in .zcb/clickpacks");
-                    },
+        ui.horizontal(|ui| {
+            if ui
+                .button("Reset")
+                .on_hover_text("Use the default patterns")
+                .clicked()
+            {
+                self.conf.folder_rules = FolderRules::default();
+            }
+            self.show_reload_clickpack_button(ui);
+        });
+    }
+
+    fn export_sorted_clickpack(&self) {
+        let Some(path) = self.clickpack_path.clone() else {
+            return;
+        };
+        let name = if is_zip_file(&path) {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        let name = name.unwrap_or_default().to_string_lossy();
+        let out = Path::new(".zcb/clickpacks").join(format!("{name} (sorted)"));
+        let (rules, settings) = (self.conf.folder_rules.clone(), self.conf.auto_sort.clone());
+        let toast_queue = self.toast_queue.clone();
+        std::thread::spawn(move || {
+            let toast = match autosort::export_sorted(&path, &out, &rules, &settings) {
+                Ok(num_sounds) => {
+                    let _ = unsafe { BOT.reload_clickpacks() }
+                        .map_err(|e| log::error!("failed to reload clickpacks: {e}"));
+                    Toast {
+                        kind: ToastKind::Success,
+                        text: format!("Saved {num_sounds} sounds to {}", out.display()).into(),
+                        options: ToastOptions::default().duration_in_seconds(3.0),
+                    }
+                }
+                Err(e) => {
+                    log::error!("failed to export sorted clickpack to {out:?}: {e}");
+                    Toast {
+                        kind: ToastKind::Error,
+                        text: format!("Failed to export clickpack: {e}").into(),
+                        options: ToastOptions::default().duration_in_seconds(5.0),
+                    }
+                }
+            };
+            toast_queue.lock().unwrap().push(toast);
+        });
+    }
+
+    fn show_auto_sort(&mut self, ui: &mut egui::Ui) {
+        ui.label("Sort clickpacks that are just a folder of recordings");
+        let settings = &mut self.conf.auto_sort;
+        help_text(
+            ui,
+            "Sounds are sorted by loudness and attack when the files\n\
+            can't be sorted by their folder or file names",
+            |ui| ui.checkbox(&mut settings.enabled, "Sort on load"),
+        );
+        drag_value(
+            ui,
+            &mut settings.tiers,
+            "Loudness tiers",
+            1..=4,
+            "1 only has regular clicks,\n\
+            4 has hard, regular, soft and micro clicks",
+        );
+        ui.checkbox(&mut settings.releases, "Detect releases")
+            .on_hover_text("Releases usually have a softer attack than clicks");
+        ui.horizontal(|ui| {
+            self.show_reload_clickpack_button(ui);
+            if ui
+                .add_enabled(
+                    self.clickpack_path.is_some(),
+                    egui::Button::new("Export sorted clickpack"),
+                )
+                .on_hover_text("Copy the sounds into a new clickpack with category folders")
+                .clicked()
+            {
+                self.export_sorted_clickpack();
+            }
+        });
+    }
+
+    fn build_clickpack(&self) {
+        let (settings, sort) = (self.conf.builder.clone(), self.conf.auto_sort.clone());
+        let toast_queue = self.toast_queue.clone();
+        std::thread::spawn(move || {
+            let Some(recording) = FileDialog::new().add_filter("WAV", &["wav"]).pick_file() else {
+                return;
+            };
+            let name = recording.file_stem().unwrap_or_default().to_string_lossy();
+            let out = Path::new(".zcb/clickpacks").join(name.as_ref());
+            let toast = match builder::build_clickpack(&recording, &out, &settings, &sort) {
+                Ok(num_clicks) => {
+                    let _ = unsafe { BOT.reload_clickpacks() }
+                        .map_err(|e| log::error!("failed to reload clickpacks: {e}"));
+                    Toast {
+                        kind: ToastKind::Success,
+                        text: format!("Saved {num_clicks} clicks to {}", out.display()).into(),
+                        options: ToastOptions::default().duration_in_seconds(3.0),
+                    }
+                }
+                Err(e) => {
+                    log::error!("failed to build clickpack from {recording:?}: {e}");
+                    Toast {
+                        kind: ToastKind::Error,
+                        text: format!("Failed to build clickpack: {e}").into(),
+                        options: ToastOptions::default().duration_in_seconds(5.0),
+                    }
+                }
+            };
+            toast_queue.lock().unwrap().push(toast);
+        });
+    }
+
+    fn show_clickpack_builder(&mut self, ui: &mut egui::Ui) {
+        ui.label("Cut a recording of someone clicking into a new clickpack");
+        let settings = &mut self.conf.builder;
+        drag_value(
+            ui,
+            &mut settings.threshold,
+            "Threshold (dB)",
+            -80.0..=0.0,
+            "How much quieter than the loudest click a click can be",
+        );
+        drag_value(
+            ui,
+            &mut settings.min_gap,
+            "Minimum gap (ms)",
+            1.0..=1000.0,
+            "Shortest time between two clicks",
+        );
+        drag_value(
+            ui,
+            &mut settings.pre_roll,
+            "Pre-roll (ms)",
+            0.0..=100.0,
+            "How much of the recording before every click is kept",
+        );
+        drag_value(
+            ui,
+            &mut settings.tail,
+            "Tail (ms)",
+            1.0..=2000.0,
+            "How long a click can be at most",
+        );
+        help_text(
+            ui,
+            "Clicks are sorted into categories with the auto-sort settings.\n\
+            The clickpack is saved in .zcb/clickpacks",
+            |ui| {
+                if ui.button("Build from recording").clicked() {
+                    self.build_clickpack();
+                }
+            },
+        );
+    }
+
+    /// Play a sound once through the active backend, without any of the
+    /// pitch or volume changes of clicks.
+    fn play_preview(&mut self, sound: SoundWrapper) {
+        let volume = sound.gain * self.volume_settings().global_volume;
+        if self.conf.use_fmod {
+            let mut channel = std::ptr::null_mut();
+            unsafe {
+                FMOD_System_PlaySound(
+                    self.system,
+                    sound.fmod_sound,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut channel,
                 );
+                FMOD_Channel_SetVolume(channel, volume);
+                FMOD_System_Update(self.system);
             }
+        } else {
+            let mut sound = sound.sound;
+            sound.set_volume(volume);
+            self.mixer.play(sound);
+        }
+    }
 
-            let show_open_folder = |ui: &mut egui::Ui| {
+    fn stop_preview_noise(&mut self) {
+        if let Some(voice) = self.preview_noise.take() {
+            voice.stop();
+        }
+    }
+
+    fn show_preview(&mut self, ui: &mut egui::Ui) {
+        let mut play = None;
+        ui.horizontal(|ui| {
+            if let Some(noise) = &self.noise {
+                let playing = self.preview_noise.is_some();
                 if ui
-                    .button("Open folder")
-                    .on_hover_text("Open .zcb/clickpacks")
+                    .button(if playing { "Stop noise" } else { "Play noise" })
+                    .on_hover_text(format!("Play {} on its own", noise.path.display()))
                     .clicked()
                 {
-                    let _ = std::fs::create_dir_all(".zcb/clickpacks")
-                        .map_err(|e| log::error!("failed to create .zcb/clickpacks: {e}"));
-                    Command::new("explorer")
-                        .arg(".zcb\\clickpacks")
-                        .spawn()
-                        .unwrap();
+                    if playing {
+                        self.stop_preview_noise();
+                    } else {
+                        let voice =
+                            self.start_noise_voice(noise.clone(), self.conf.noise_volume, 1.0);
+                        self.preview_noise = Some(voice);
+                    }
                 }
-            };
+            } else {
+                ui.label("No noise file");
+            }
+        });
 
-            let mut is_combobox = false;
-            ui.horizontal(|ui| {
-                is_combobox = self.select_clickpack_button(ui, modal);
-                if !self.selected_clickpack.is_empty() {
-                    ui.style_mut().spacing.item_spacing.x = 4.0;
-                    if ui.button("🗙").on_hover_text("Unload clickpack").clicked() {
-                        self.unload_clickpack();
+        for (player2, sounds) in [(false, &self.players.0), (true, &self.players.1)] {
+            if player2 && !sounds.has_sounds() {
+                continue;
+            }
+            let player = if player2 { 2 } else { 1 };
+            ui.collapsing(format!("Player {player}"), |ui| {
+                for (typ, clicks) in sounds.categories() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{typ:?}: {} sounds", clicks.len()));
+                        if ui
+                            .add_enabled(!clicks.is_empty(), egui::Button::new("Play random"))
+                            .clicked()
+                        {
+                            play = clicks.choose(&mut rand::thread_rng()).cloned();
+                        }
+                    });
+                    if clicks.is_empty() {
+                        continue;
                     }
-                }
-                if is_combobox {
-                    show_open_folder(ui);
+                    ui.push_id(typ as usize, |ui| {
+                        ui.collapsing("Sounds", |ui| {
+                            egui::ScrollArea::vertical()
+                                .max_height(150.0)
+                                .show(ui, |ui| {
+                                    for (i, click) in clicks.iter().enumerate() {
+                                        ui.horizontal(|ui| {
+                                            if ui.button("▶").clicked() {
+                                                play = Some(click.clone());
+                                            }
+                                            let name = click.path.file_name().unwrap_or_default();
+                                            let selected =
+                                                self.preview_selected == Some((player2, typ, i));
+                                            if ui
+                                                .selectable_label(selected, name.to_string_lossy())
+                                                .clicked()
+                                            {
+                                                self.preview_selected =
+                                                    (!selected).then_some((player2, typ, i));
+                                            }
+                                        });
+                                    }
+                                });
+                        });
+                    });
                 }
             });
-            if !is_combobox {
+        }
+
+        // waveform and stats of the selected sound
+        let selected = self.preview_selected.and_then(|(player2, typ, i)| {
+            let sounds = if player2 {
+                &self.players.1
+            } else {
+                &self.players.0
+            };
+            sounds.sounds(typ).get(i)
+        });
+        if let Some(click) = selected {
+            let seconds = click.frames.len() as f32 / click.sample_rate().max(1) as f32;
+            ui.label(format!(
+                "{}: {:.0} ms, {} Hz, peak {:.1} dBFS",
+                click.path.display(),
+                seconds * 1000.0,
+                click.sample_rate(),
+                dsp::peak_db(&click.frames)
+            ));
+            show_waveform(ui, &click.frames);
+        }
+
+        if let Some(click) = play {
+            self.play_preview(click);
+        }
+    }
+
+    fn show_clickpack_window(&mut self, ui: &mut egui::Ui, modal: Arc<Mutex<Modal>>) {
+        self.show_load_progress(ui);
+        let has_sounds = self.num_sounds != (0, 0);
+
+        // picking a clickpack while another one is loading cancels it
+        if !self.clickpacks.is_empty() {
+            help_text(
+                ui,
+                "Clickpacks can be folders or .zip archives.\n\
+                If there's no clickpacks inside .zcb/clickpacks,\n\
+                there will be an option to choose the clickpack manually",
+                |ui| {
+                    ui.label("Put clickpacks in .zcb/clickpacks");
+                },
+            );
+        }
+
+        let show_open_folder = |ui: &mut egui::Ui| {
+            if ui
+                .button("Open folder")
+                .on_hover_text("Open .zcb/clickpacks")
+                .clicked()
+            {
+                let _ = std::fs::create_dir_all(".zcb/clickpacks")
+                    .map_err(|e| log::error!("failed to create .zcb/clickpacks: {e}"));
+                open_in_explorer(".zcb\\clickpacks");
+            }
+        };
+
+        let mut is_combobox = false;
+        ui.horizontal(|ui| {
+            is_combobox = self.select_clickpack_button(ui, modal);
+            if !self.selected_clickpack.is_empty() {
+                ui.style_mut().spacing.item_spacing.x = 4.0;
+                if ui
+                    .add_enabled(!self.is_loading_clickpack, egui::Button::new("🗙"))
+                    .on_hover_text("Unload clickpack")
+                    .clicked()
+                {
+                    self.unload_clickpack();
+                }
+            }
+            if is_combobox {
                 show_open_folder(ui);
             }
         });
+        if !is_combobox {
+            show_open_folder(ui);
+        }
 
         if has_sounds {
             help_text(
@@ -2146,9 +5139,32 @@ impl Bot {
             );
         }
 
+        if has_sounds {
+            self.show_manifest_info(ui);
+            ui.add_enabled_ui(self.watcher.is_some(), |ui| {
+                help_text(
+                    ui,
+                    "Load the clickpack again when its files are added,\n\
+                    removed or changed. Only the changed files are decoded",
+                    |ui| ui.checkbox(&mut self.conf.auto_reload_clickpack, "Reload on changes"),
+                );
+            });
+            self.show_clickpack_report(ui);
+            ui.collapsing("Preview", |ui| self.show_preview(ui));
+            ui.collapsing("Folders", |ui| self.show_folders(ui))
+                .header_response
+                .on_hover_text("Which folder every category was loaded from");
+            ui.collapsing("Layers", |ui| self.show_clickpack_layers(ui));
+            self.show_player2_clickpack(ui);
+        }
+        ui.collapsing("Folder names", |ui| self.show_folder_rules(ui));
+        ui.collapsing("Auto-sort", |ui| self.show_auto_sort(ui));
+        ui.collapsing("Clickpack builder", |ui| self.show_clickpack_builder(ui));
+
         if !self.is_loading_clickpack && has_sounds && !self.playlayer.is_null() {
             ui.separator();
             ui.collapsing("Debug", |ui| {
+                let (pitch, vol) = (self.pitch(), self.volume_settings());
                 let dur = Duration::from_secs_f64(self.prev_time);
                 let ago = self.time() - dur.as_secs_f64();
                 help_text(ui, &format!("{dur:?} since the start of the level"), |ui| {
@@ -2164,13 +5180,11 @@ impl Bot {
                 }
                 ui.label(format!(
                     "Last pitch: {:.4} ({} => {})",
-                    self.prev_pitch, self.conf.pitch.from, self.conf.pitch.to
+                    self.prev_pitch, pitch.from, pitch.to
                 ));
                 ui.label(format!(
                     "Last volume: {:.4} (+/- {} * {})",
-                    self.prev_volume,
-                    self.conf.volume_settings.volume_var,
-                    self.conf.volume_settings.global_volume
+                    self.prev_volume, vol.volume_var, vol.global_volume
                 ));
                 ui.label(format!(
                     "Last spam volume offset: {:.4}",
@@ -2187,3 +5201,94 @@ impl Drop for Bot {
         self.release_fmod()
     }
 }
+
+#[test]
+fn test_manifest_overrides() {
+    // only one end of the range makes it inverted
+    let manifest: ClickpackManifest = serde_json::from_str(r#"{"pitch": {"from": 1.2}}"#).unwrap();
+    let mut pitch = Pitch::default();
+    manifest.apply_pitch(&mut pitch);
+    assert_eq!((pitch.from, pitch.to), (1.2, 1.2));
+
+    let manifest: ClickpackManifest =
+        serde_json::from_str(r#"{"pitch": {"to": 0.5, "step": -1.0}}"#).unwrap();
+    let mut pitch = Pitch::default();
+    manifest.apply_pitch(&mut pitch);
+    assert_eq!((pitch.from, pitch.to), (0.5, 0.5));
+    assert_eq!(pitch.step, Pitch::default().step);
+
+    let manifest: ClickpackManifest =
+        serde_json::from_str(r#"{"volume_settings": {"volume_var": -0.1}}"#).unwrap();
+    let mut vol = VolumeSettings::default();
+    manifest.apply_volume_settings(&mut vol);
+    assert_eq!(vol.volume_var, 0.1);
+}
+
+#[test]
+fn test_fmod_samples() {
+    let frames: Vec<Frame> = (0..22_050)
+        .map(|i| Frame {
+            left: (i as f32 * 0.01).sin(),
+            right: 0.0,
+        })
+        .collect();
+    let sound = Sound::from_frames(22_050, &frames);
+
+    // resampled to the rate of the fmod mixer
+    let (samples, sample_rate) = SoundWrapper::fmod_samples(&sound, true);
+    assert_eq!(sample_rate, dsp::CANONICAL_SAMPLE_RATE);
+    assert_eq!(samples.len(), 2 * dsp::CANONICAL_SAMPLE_RATE as usize);
+    assert!(samples.chunks_exact(2).all(|f| f[1] == 0.0));
+
+    // left at the rate of the sound
+    let (samples, sample_rate) = SoundWrapper::fmod_samples(&sound, false);
+    assert_eq!(sample_rate, 22_050);
+    assert_eq!(samples, dsp::interleave(&frames));
+}
+
+#[test]
+fn test_bank_fallback() {
+    let loader = SoundLoader::new(std::ptr::null_mut());
+    let sound = |name: &str, button: Option<Button>| {
+        let frames = vec![
+            Frame {
+                left: 0.0,
+                right: 0.0
+            };
+            100
+        ];
+        let mut click =
+            SoundWrapper::from_decoded(&loader, Sound::from_frames(44_100, &frames), name.as_ref());
+        click.button = button;
+        click
+    };
+
+    // the jump folder only has clicks, releases come from the normal categories
+    let mut sounds = Sounds {
+        clicks: vec![sound("jump/click.wav", Some(Button::Jump))],
+        releases: vec![sound("release.wav", None)],
+        ..Default::default()
+    };
+    assert_eq!(sounds.num_sounds(), 1);
+    assert_eq!(sounds.num_bank_sounds(), 1);
+
+    let mut pick = |typ, button| {
+        let (click, typ) = sounds
+            .random_sound(typ, SelectionMode::Random, 0, button)
+            .unwrap();
+        (click.path, typ)
+    };
+    assert_eq!(
+        pick(ClickType::Click, Button::Jump),
+        (PathBuf::from("jump/click.wav"), ClickType::Click)
+    );
+    assert_eq!(
+        pick(ClickType::Release, Button::Jump),
+        (PathBuf::from("release.wav"), ClickType::Release)
+    );
+    // other buttons never play the clicks of the jump folder
+    assert_eq!(
+        pick(ClickType::Click, Button::Left),
+        (PathBuf::from("release.wav"), ClickType::Release)
+    );
+}
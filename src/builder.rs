@@ -0,0 +1,172 @@
+use crate::{
+    autosort::{self, AutoSortSettings, Features},
+    bot::ClickpackFs,
+    dsp,
+};
+use anyhow::Result;
+use kittyaudio::Frame;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Length of the blocks that the level of a recording is measured in.
+const BLOCK_MS: f32 = 1.0;
+/// How far back the background level is measured.
+const BACKGROUND_MS: f32 = 10.0;
+/// How much louder than the background a block has to be to start a click.
+const RISE_DB: f32 = 6.0;
+/// Fade-out at the end of every slice, so it doesn't end with a pop.
+const FADE_MS: f32 = 5.0;
+
+/// Settings for cutting a recording of someone clicking into clicks.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct BuilderSettings {
+    /// Level below the loudest click that still counts as a click, in dB.
+    pub threshold: f32,
+    /// Shortest time between two clicks in milliseconds.
+    pub min_gap: f32,
+    /// How much of the recording before a click is kept, in milliseconds.
+    pub pre_roll: f32,
+    /// How long a click can be at most, in milliseconds.
+    pub tail: f32,
+}
+
+impl Default for BuilderSettings {
+    fn default() -> Self {
+        Self {
+            threshold: -30.0,
+            min_gap: 30.0,
+            pre_roll: 2.0,
+            tail: 150.0,
+        }
+    }
+}
+
+impl BuilderSettings {
+    /// Frames where clicks start.
+    pub fn detect_onsets(&self, frames: &[Frame], sample_rate: u32) -> Vec<usize> {
+        let block = dsp::ms_to_frames(BLOCK_MS, sample_rate).max(1);
+        let levels: Vec<f32> = frames
+            .chunks(block)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|&f| dsp::frame_peak(f))
+                    .fold(0.0, f32::max)
+            })
+            .collect();
+        let peak = levels.iter().copied().fold(0.0, f32::max);
+        let threshold = peak * dsp::db_to_linear(self.threshold);
+        let background_len = (BACKGROUND_MS / BLOCK_MS) as usize;
+        let rise = dsp::db_to_linear(RISE_DB);
+        let min_gap = dsp::ms_to_frames(self.min_gap, sample_rate);
+
+        let mut onsets: Vec<usize> = vec![];
+        for (i, &level) in levels.iter().enumerate() {
+            if level < threshold || level <= 0.0 {
+                continue;
+            }
+            // only where the level jumps up, not while a click rings out
+            let history = &levels[i.saturating_sub(background_len)..i];
+            let background = history.iter().sum::<f32>() / history.len().max(1) as f32;
+            let trigger = threshold.max(background * rise);
+            if level < trigger {
+                continue;
+            }
+            let start = i * block;
+            let onset = start
+                + frames[start..]
+                    .iter()
+                    .position(|&f| dsp::frame_peak(f) >= trigger)
+                    .unwrap_or(0);
+            if onsets.last().is_some_and(|&last| onset - last < min_gap) {
+                continue;
+            }
+            onsets.push(onset);
+        }
+        onsets
+    }
+
+    /// Cut a recording into clicks. Every click ends where the next one starts,
+    /// or after the tail.
+    pub fn slice(&self, frames: &[Frame], sample_rate: u32) -> Vec<Vec<Frame>> {
+        let onsets = self.detect_onsets(frames, sample_rate);
+        let pre_roll = dsp::ms_to_frames(self.pre_roll, sample_rate);
+        let tail = dsp::ms_to_frames(self.tail, sample_rate).max(1);
+        let fade = dsp::ms_to_frames(FADE_MS, sample_rate);
+        onsets
+            .iter()
+            .enumerate()
+            .map(|(i, &onset)| {
+                let start = onset.saturating_sub(pre_roll);
+                let mut end = (onset + tail).min(frames.len());
+                if let Some(&next) = onsets.get(i + 1) {
+                    end = end.min(next.saturating_sub(pre_roll).max(onset + 1));
+                }
+                let mut click = frames[start..end].to_vec();
+                let fade = fade.min(click.len() / 2);
+                dsp::fade_out(&mut click, fade);
+                click
+            })
+            .collect()
+    }
+}
+
+/// Cut a recording into clicks and write them into a new clickpack at `out`,
+/// sorted into categories like [autosort::categorize]. Returns the number of
+/// clicks.
+pub fn build_clickpack(
+    recording: &Path,
+    out: &Path,
+    settings: &BuilderSettings,
+    sort: &AutoSortSettings,
+) -> Result<usize> {
+    anyhow::ensure!(!out.exists(), "{} already exists", out.display());
+    let sound = ClickpackFs::Dir.decode(None, None, recording)?;
+    let sample_rate = sound.sample_rate();
+    let clicks = settings.slice(&sound.frames, sample_rate);
+    anyhow::ensure!(!clicks.is_empty(), "no clicks found in recording");
+    log::info!("found {} clicks in {recording:?}", clicks.len());
+
+    let features: Vec<Features> = clicks
+        .iter()
+        .map(|click| Features::measure(click, sample_rate))
+        .collect();
+    let types = autosort::categorize(&features, sort);
+    for (i, (click, typ)) in clicks.iter().zip(types).enumerate() {
+        let dir = out.join(typ.dirname());
+        std::fs::create_dir_all(&dir)?;
+        let f = std::fs::File::create(dir.join(format!("{:03}.wav", i + 1)))?;
+        dsp::write_wav(std::io::BufWriter::new(f), click, sample_rate)?;
+    }
+    Ok(clicks.len())
+}
+
+#[test]
+fn test_slice() {
+    // three decaying clicks on top of quiet noise
+    let sample_rate = 48_000;
+    let mut frames: Vec<Frame> = (0..sample_rate)
+        .map(|i| {
+            let x = if i % 2 == 0 { 0.001 } else { -0.001 };
+            Frame { left: x, right: x }
+        })
+        .collect();
+    let onsets = [4800, 20_000, 22_000];
+    for &onset in &onsets {
+        for i in 0..4800 {
+            let x = (-(i as f32) / 300.0).exp() * if i % 2 == 0 { 0.5 } else { -0.5 };
+            frames[onset + i].left += x;
+            frames[onset + i].right += x;
+        }
+    }
+
+    let settings = BuilderSettings::default();
+    assert_eq!(settings.detect_onsets(&frames, sample_rate), onsets);
+    let clicks = settings.slice(&frames, sample_rate);
+    let pre_roll = dsp::ms_to_frames(settings.pre_roll, sample_rate);
+    let tail = dsp::ms_to_frames(settings.tail, sample_rate);
+    assert_eq!(clicks[0].len(), pre_roll + tail);
+    // the second click is cut off where the third one starts
+    assert_eq!(clicks[1].len(), 2000);
+    assert_eq!(clicks[2].len(), pre_roll + tail);
+}
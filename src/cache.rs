@@ -0,0 +1,209 @@
+use kittyaudio::{Frame, Sound};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Maximum size of the cache in megabytes.
+    pub max_size: u32,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size: 128,
+        }
+    }
+}
+
+impl CacheSettings {
+    #[inline]
+    pub fn max_bytes(&self) -> u64 {
+        self.max_size as u64 * 1024 * 1024
+    }
+}
+
+/// 64-bit FNV-1a. Stable between builds, unlike [std::hash::DefaultHasher].
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for &b in data {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Cache of decoded (and processed) sounds, so loading a clickpack doesn't
+/// decode every file again. Every sound is stored in its own file, named after
+/// the hash of its key.
+///
+/// Keys contain the path, size, modification time and a hash of the contents of
+/// the file, so entries of changed files are never used again. They are removed
+/// by [PcmCache::trim] once the cache gets too big, least recently used first.
+#[derive(Clone)]
+pub struct PcmCache {
+    dir: PathBuf,
+}
+
+impl Default for PcmCache {
+    fn default() -> Self {
+        Self::new(Self::DIR)
+    }
+}
+
+impl PcmCache {
+    pub const DIR: &'static str = ".zcb/cache/pcm";
+    const MAGIC: &'static [u8; 8] = b"ZCBPCM1\0";
+    const EXTENSION: &'static str = "pcm";
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache key of a file. `file_key` identifies the file (see
+    /// [crate::bot::ClickpackFs::file_key]), `data` is its contents and `salt`
+    /// should change whenever the decoded audio can change.
+    pub fn key(file_key: &str, data: &[u8], salt: &str) -> String {
+        format!("{file_key}|{:016x}|{salt}", fnv1a(data))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{:016x}.{}",
+            fnv1a(key.as_bytes()),
+            Self::EXTENSION
+        ))
+    }
+
+    /// Read a sound from the cache. Returns [None] if it isn't cached or the entry
+    /// is invalid.
+    pub fn get(&self, key: &str) -> Option<Sound> {
+        let path = self.entry_path(key);
+        let data = std::fs::read(&path).ok()?;
+        let Some(sound) = Self::decode_entry(key, &data) else {
+            log::warn!("removing invalid cache entry {path:?}");
+            let _ = std::fs::remove_file(&path);
+            return None;
+        };
+
+        // used for least recently used eviction
+        let _ = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        Some(sound)
+    }
+
+    fn decode_entry(key: &str, data: &[u8]) -> Option<Sound> {
+        let data = data.strip_prefix(Self::MAGIC)?;
+        let (key_len, data) = data.split_first_chunk::<4>()?;
+        let (stored_key, data) = data.split_at_checked(u32::from_le_bytes(*key_len) as usize)?;
+        // different keys can have the same hash
+        if stored_key != key.as_bytes() {
+            return None;
+        }
+        let (sample_rate, data) = data.split_first_chunk::<4>()?;
+        if data.len() % 8 != 0 {
+            return None;
+        }
+        let frames: Vec<Frame> = data
+            .chunks_exact(8)
+            .map(|f| Frame {
+                left: f32::from_le_bytes(f[..4].try_into().unwrap()),
+                right: f32::from_le_bytes(f[4..].try_into().unwrap()),
+            })
+            .collect();
+        Some(Sound::from_frames(
+            u32::from_le_bytes(*sample_rate),
+            &frames,
+        ))
+    }
+
+    /// Write a sound to the cache.
+    pub fn insert(&self, key: &str, sound: &Sound) {
+        if let Err(e) = self.try_insert(key, sound) {
+            log::error!("failed to write cache entry: {e}");
+        }
+    }
+
+    fn try_insert(&self, key: &str, sound: &Sound) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut data = Vec::with_capacity(16 + key.len() + sound.frames.len() * 8);
+        data.extend_from_slice(Self::MAGIC);
+        data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        data.extend_from_slice(key.as_bytes());
+        data.extend_from_slice(&sound.sample_rate().to_le_bytes());
+        for frame in sound.frames.iter() {
+            data.extend_from_slice(&frame.left.to_le_bytes());
+            data.extend_from_slice(&frame.right.to_le_bytes());
+        }
+
+        // write to a temporary file first so a crash never leaves a broken entry
+        let path = self.entry_path(key);
+        let tmp = path.with_extension("tmp");
+        std::fs::File::create(&tmp)?.write_all(&data)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    /// Cache entries with their size and last use time.
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Ok(entries) = self.dir.read_dir() else {
+            return vec![];
+        };
+        entries
+            .filter_map(|e| {
+                let path = e.ok()?.path();
+                if path.extension()? != Self::EXTENSION {
+                    return None;
+                }
+                let meta = path.metadata().ok()?;
+                Some((path, meta.len(), meta.modified().ok()?))
+            })
+            .collect()
+    }
+
+    /// Total size of the cache in bytes.
+    pub fn size(&self) -> u64 {
+        self.entries().iter().map(|(_, size, _)| size).sum()
+    }
+
+    /// Remove least recently used entries until the cache is at most `max_bytes`.
+    pub fn trim(&self, max_bytes: u64) {
+        let mut entries = self.entries();
+        let mut size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if size <= max_bytes {
+            return;
+        }
+        entries.sort_by_key(|(_, _, used)| *used);
+        for (path, len, _) in entries {
+            if size <= max_bytes {
+                break;
+            }
+            if Self::remove(&path) {
+                size -= len;
+            }
+        }
+        log::debug!("trimmed pcm cache to {size} bytes");
+    }
+
+    /// Remove every entry, returns the number of bytes freed.
+    pub fn clear(&self) -> u64 {
+        self.entries()
+            .into_iter()
+            .filter(|(path, _, _)| Self::remove(path))
+            .map(|(_, size, _)| size)
+            .sum()
+    }
+
+    fn remove(path: &Path) -> bool {
+        std::fs::remove_file(path)
+            .map_err(|e| log::error!("failed to remove {path:?}: {e}"))
+            .is_ok()
+    }
+}
@@ -0,0 +1,493 @@
+//! Small helpers for processing decoded audio.
+
+use kittyaudio::Frame;
+use serde::{Deserialize, Serialize};
+
+#[inline]
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[inline]
+pub fn ms_to_frames(ms: f32, sample_rate: u32) -> usize {
+    (ms.max(0.0) / 1000.0 * sample_rate as f32).round() as usize
+}
+
+/// Absolute peak of both channels of a frame.
+#[inline]
+pub fn frame_peak(frame: Frame) -> f32 {
+    frame.left.abs().max(frame.right.abs())
+}
+
+/// Index of the first frame louder than `threshold` (linear), or [None] if the
+/// whole sound is below it.
+pub fn first_audible_frame(frames: &[Frame], threshold: f32) -> Option<usize> {
+    frames.iter().position(|&f| frame_peak(f) > threshold)
+}
+
+/// Linear fade from silence over the first `len` frames.
+pub fn fade_in(frames: &mut [Frame], len: usize) {
+    let len = len.min(frames.len());
+    for (i, frame) in frames[..len].iter_mut().enumerate() {
+        let gain = i as f32 / len as f32;
+        frame.left *= gain;
+        frame.right *= gain;
+    }
+}
+
+/// Linear fade to silence over the last `len` frames.
+pub fn fade_out(frames: &mut [Frame], len: usize) {
+    let len = len.min(frames.len());
+    let start = frames.len() - len;
+    for (i, frame) in frames[start..].iter_mut().enumerate() {
+        let gain = (len - i) as f32 / len as f32;
+        frame.left *= gain;
+        frame.right *= gain;
+    }
+}
+
+/// Levels of a sound in dBFS (LUFS for `lufs`). Silent sounds are clamped to
+/// [Loudness::SILENCE].
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Loudness {
+    pub peak: f32,
+    pub rms: f32,
+    pub lufs: f32,
+}
+
+impl Loudness {
+    pub const SILENCE: f32 = -200.0;
+
+    pub fn measure(frames: &[Frame], sample_rate: u32) -> Self {
+        Self {
+            peak: peak_db(frames),
+            rms: rms_db(frames),
+            lufs: lufs(frames, sample_rate),
+        }
+    }
+}
+
+#[inline]
+fn to_db(linear: f32) -> f32 {
+    (20.0 * linear.log10()).max(Loudness::SILENCE)
+}
+
+pub fn peak_db(frames: &[Frame]) -> f32 {
+    to_db(frames.iter().map(|&f| frame_peak(f)).fold(0.0, f32::max))
+}
+
+/// Number of samples in runs of at least `min_run` samples at (or very close to)
+/// full scale, which is what clipping looks like.
+pub fn clipped_samples(frames: &[Frame], min_run: usize) -> usize {
+    const FULL_SCALE: f32 = 0.999;
+    let mut clipped = 0;
+    for channel in [|f: &Frame| f.left, |f: &Frame| f.right] {
+        let mut run = 0;
+        for frame in frames {
+            if channel(frame).abs() >= FULL_SCALE {
+                run += 1;
+                continue;
+            }
+            if run >= min_run {
+                clipped += run;
+            }
+            run = 0;
+        }
+        if run >= min_run {
+            clipped += run;
+        }
+    }
+    clipped
+}
+
+pub fn rms_db(frames: &[Frame]) -> f32 {
+    if frames.is_empty() {
+        return Loudness::SILENCE;
+    }
+    let sum: f64 = frames
+        .iter()
+        .map(|f| (f.left as f64).powi(2) + (f.right as f64).powi(2))
+        .sum();
+    to_db((sum / (frames.len() * 2) as f64).sqrt() as f32)
+}
+
+/// Second order IIR filter (transposed direct form II).
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// K-weighting filter from ITU-R BS.1770 for any sample rate (coefficients as in
+/// libebur128).
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    // high shelf
+    let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    // high pass
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, highpass]
+}
+
+/// Second order Butterworth low-pass filter, applied to both channels.
+pub fn lowpass(frames: &mut [Frame], cutoff: f32, sample_rate: u32) {
+    let fs = sample_rate as f64;
+    let k = (std::f64::consts::PI * (cutoff as f64).clamp(1.0, fs * 0.49) / fs).tan();
+    let q = std::f64::consts::FRAC_1_SQRT_2;
+    let a0 = 1.0 + k / q + k * k;
+    let filter = Biquad {
+        b: [k * k / a0, 2.0 * k * k / a0, k * k / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+    let (mut left, mut right) = (filter, filter);
+    for frame in frames {
+        frame.left = left.process(frame.left as f64) as f32;
+        frame.right = right.process(frame.right as f64) as f32;
+    }
+}
+
+/// Integrated loudness in LUFS (ITU-R BS.1770 with absolute and relative gating).
+/// Sounds shorter than one 400ms block are measured as a single block.
+pub fn lufs(frames: &[Frame], sample_rate: u32) -> f32 {
+    if frames.is_empty() || sample_rate == 0 {
+        return Loudness::SILENCE;
+    }
+
+    // K-weighted power of every frame, summed over both channels
+    let [mut l1, mut l2] = k_weighting(sample_rate);
+    let [mut r1, mut r2] = k_weighting(sample_rate);
+    let power: Vec<f64> = frames
+        .iter()
+        .map(|f| {
+            let l = l2.process(l1.process(f.left as f64));
+            let r = r2.process(r1.process(f.right as f64));
+            l * l + r * r
+        })
+        .collect();
+
+    // 400ms blocks with 75% overlap
+    let block_len = (sample_rate as usize * 2 / 5).clamp(1, power.len());
+    let step = (block_len / 4).max(1);
+    let mut blocks = vec![];
+    let mut start = 0;
+    while start + block_len <= power.len() {
+        let sum: f64 = power[start..start + block_len].iter().sum();
+        blocks.push(sum / block_len as f64);
+        start += step;
+    }
+
+    let block_lufs = |ms: f64| -0.691 + 10.0 * ms.log10();
+    let gated_mean = |threshold: f64| {
+        let gated: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&ms| block_lufs(ms) > threshold)
+            .collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+
+    // absolute gate at -70 LUFS, then relative gate at -10 LU
+    let Some(mean) = gated_mean(-70.0) else {
+        return Loudness::SILENCE;
+    };
+    let Some(mean) = gated_mean(block_lufs(mean) - 10.0) else {
+        return Loudness::SILENCE;
+    };
+    (block_lufs(mean) as f32).max(Loudness::SILENCE)
+}
+
+/// Sample rate of the FMOD software mixer, sounds are converted to it so FMOD
+/// never has to guess the format.
+pub const CANONICAL_SAMPLE_RATE: u32 = 48_000;
+
+/// Half the number of taps of the resampling filter.
+const RESAMPLE_TAPS: isize = 16;
+
+#[inline]
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        let x = std::f64::consts::PI * x;
+        x.sin() / x
+    }
+}
+
+/// Number of frames a sound of `frames` frames has after resampling.
+#[inline]
+pub fn resampled_len(frames: usize, from: u32, to: u32) -> usize {
+    ((frames as u64 * to as u64 + from as u64 / 2) / from as u64) as usize
+}
+
+/// Resample a single channel with a Lanczos windowed sinc filter.
+pub fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || input.is_empty() || from == 0 {
+        return input.to_vec();
+    }
+    let ratio = to as f64 / from as f64;
+    // lower the cutoff when downsampling to avoid aliasing
+    let cutoff = ratio.min(1.0);
+    let taps = (RESAMPLE_TAPS as f64 / cutoff).ceil() as isize;
+
+    (0..resampled_len(input.len(), from, to))
+        .map(|i| {
+            let t = i as f64 / ratio;
+            let center = t.floor() as isize;
+            let mut sum = 0.0;
+            for k in center - taps + 1..=center + taps {
+                let Some(&x) = usize::try_from(k).ok().and_then(|k| input.get(k)) else {
+                    continue;
+                };
+                let d = t - k as f64;
+                sum += x as f64 * cutoff * sinc(cutoff * d) * sinc(d / taps as f64);
+            }
+            sum as f32
+        })
+        .collect()
+}
+
+/// Frames as interleaved stereo samples.
+pub fn interleave(frames: &[Frame]) -> Vec<f32> {
+    frames.iter().flat_map(|f| [f.left, f.right]).collect()
+}
+
+/// Frames as interleaved stereo samples at [CANONICAL_SAMPLE_RATE].
+pub fn frames_to_canonical(frames: &[Frame], sample_rate: u32) -> Vec<f32> {
+    interleave(&resample_frames(frames, sample_rate))
+}
+
+/// Frames resampled to [CANONICAL_SAMPLE_RATE].
+pub fn resample_frames(frames: &[Frame], sample_rate: u32) -> Vec<Frame> {
+    if sample_rate == CANONICAL_SAMPLE_RATE {
+        return frames.to_vec();
+    }
+    let (left, right): (Vec<f32>, Vec<f32>) = frames.iter().map(|f| (f.left, f.right)).unzip();
+    let left = resample(&left, sample_rate, CANONICAL_SAMPLE_RATE);
+    let right = resample(&right, sample_rate, CANONICAL_SAMPLE_RATE);
+    left.into_iter()
+        .zip(right)
+        .map(|(left, right)| Frame { left, right })
+        .collect()
+}
+
+/// Write frames as a 16-bit stereo WAV file.
+pub fn write_wav(
+    mut w: impl std::io::Write,
+    frames: &[Frame],
+    sample_rate: u32,
+) -> std::io::Result<()> {
+    const CHANNELS: u16 = 2;
+    const BITS: u16 = 16;
+    let block_align = CHANNELS * BITS / 8;
+    let data_len = (frames.len() * block_align as usize) as u32;
+
+    let mut data = Vec::with_capacity(44 + data_len as usize);
+    data.extend_from_slice(b"RIFF");
+    data.extend_from_slice(&(36 + data_len).to_le_bytes());
+    data.extend_from_slice(b"WAVEfmt ");
+    data.extend_from_slice(&16u32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes()); // pcm
+    data.extend_from_slice(&CHANNELS.to_le_bytes());
+    data.extend_from_slice(&sample_rate.to_le_bytes());
+    data.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    data.extend_from_slice(&block_align.to_le_bytes());
+    data.extend_from_slice(&BITS.to_le_bytes());
+    data.extend_from_slice(b"data");
+    data.extend_from_slice(&data_len.to_le_bytes());
+    for sample in frames.iter().flat_map(|f| [f.left, f.right]) {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        data.extend_from_slice(&sample.to_le_bytes());
+    }
+    w.write_all(&data)
+}
+
+/// Make a sound loop without a click by crossfading its last `len` frames into
+/// its first ones. The result is `len` frames shorter and wraps around smoothly.
+pub fn crossfade_loop(frames: &[Frame], len: usize) -> Vec<Frame> {
+    let len = len.min(frames.len() / 2);
+    let body = frames.len() - len;
+    let mut out = frames[..body].to_vec();
+    for (i, frame) in out[..len].iter_mut().enumerate() {
+        // equal power, noise is mostly uncorrelated
+        let t = (i as f32 + 0.5) / len as f32 * std::f32::consts::FRAC_PI_2;
+        let (fade_in, fade_out) = t.sin_cos();
+        let tail = frames[body + i];
+        frame.left = frame.left * fade_in + tail.left * fade_out;
+        frame.right = frame.right * fade_in + tail.right * fade_out;
+    }
+    out
+}
+
+/// Longest window that is compared when searching for a loop point.
+const LOOP_WINDOW: usize = 2048;
+
+/// Find where a sound should end so that its last `len` frames crossfade best
+/// into its start, searching at most `search` frames back from the end. Uses
+/// normalized cross-correlation of the crossfaded regions.
+pub fn find_loop_end(frames: &[Frame], len: usize, search: usize) -> usize {
+    let n = frames.len();
+    let len = len.min(n / 2);
+    let window = len.min(LOOP_WINDOW);
+    if window == 0 {
+        return n;
+    }
+    let mono: Vec<f32> = frames.iter().map(|f| (f.left + f.right) * 0.5).collect();
+    let head = &mono[..window];
+    let head_energy: f32 = head.iter().map(|s| s * s).sum();
+
+    // ends before 2 * len would crossfade the head with itself
+    let first = (n - search.min(n)).max(2 * len);
+    let mut best = (n, f32::MIN);
+    for end in first..=n {
+        let tail = &mono[end - len..end - len + window];
+        let (dot, energy) = tail
+            .iter()
+            .zip(head)
+            .fold((0.0, 0.0), |(dot, energy), (t, h)| {
+                (dot + t * h, energy + t * t)
+            });
+        let corr = dot / (head_energy * energy).sqrt().max(f32::EPSILON);
+        if corr > best.1 {
+            best = (end, corr);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+fn sine(frames: usize, sample_rate: u32) -> Vec<f32> {
+    (0..frames)
+        .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin() * 0.5)
+        .collect()
+}
+
+#[test]
+fn test_canonical_length() {
+    for rate in [8_000, 11_025, 22_050, 44_100, 48_000, 96_000, 192_000] {
+        for seconds in [0.1, 0.25, 1.5] {
+            let frames: Vec<Frame> = sine((seconds * rate as f64).round() as usize, rate)
+                .into_iter()
+                .map(|x| Frame { left: x, right: x })
+                .collect();
+            let out = frames_to_canonical(&frames, rate);
+            assert_eq!(out.len() % 2, 0, "output must be interleaved stereo");
+
+            // compare against the real length of the input, `seconds` may not
+            // be a whole number of input frames
+            let seconds = frames.len() as f64 / rate as f64;
+            let out_frames = out.len() / 2;
+            let expected = (seconds * CANONICAL_SAMPLE_RATE as f64).round() as usize;
+            assert_eq!(out_frames, expected, "{rate}Hz: wrong frame count");
+            let duration = out_frames as f64 / CANONICAL_SAMPLE_RATE as f64;
+            assert!(
+                (duration - seconds).abs() <= 0.5 / CANONICAL_SAMPLE_RATE as f64,
+                "{rate}Hz: duration {duration}s, expected {seconds}s"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_frames_to_canonical() {
+    // a tone on the left and silence on the right, like a decoded sound
+    let frames: Vec<Frame> = sine(44_100, 44_100)
+        .into_iter()
+        .map(|left| Frame { left, right: 0.0 })
+        .collect();
+    let out = frames_to_canonical(&frames, 44_100);
+    assert_eq!(out.len(), 2 * CANONICAL_SAMPLE_RATE as usize);
+    assert!(out.chunks_exact(2).all(|f| f[1] == 0.0));
+    assert!(out.chunks_exact(2).any(|f| f[0].abs() > 0.4));
+
+    let out = frames_to_canonical(&frames[..4], CANONICAL_SAMPLE_RATE);
+    assert_eq!(out, interleave(&frames[..4]));
+}
+
+#[test]
+fn test_resample_keeps_level() {
+    // a 440hz sine at 0.5 amplitude should stay at the same level
+    let out = resample(&sine(44_100, 44_100), 44_100, CANONICAL_SAMPLE_RATE);
+    let peak = out[1000..out.len() - 1000]
+        .iter()
+        .fold(0f32, |a, &b| a.max(b.abs()));
+    assert!((peak - 0.5).abs() < 0.01, "peak {peak}");
+}
+
+#[test]
+fn test_crossfade_loop() {
+    // a ramp only jumps back at the loop point
+    let frames: Vec<Frame> = (0..1000)
+        .map(|i| Frame {
+            left: i as f32,
+            right: i as f32,
+        })
+        .collect();
+    let out = crossfade_loop(&frames, 100);
+    assert_eq!(out.len(), 900);
+    assert!((out[0].left - out[899].left).abs() < 2.0, "{}", out[0].left);
+    // only the start is crossfaded
+    assert_eq!(out[100..], frames[100..900]);
+}
+
+#[test]
+fn test_find_loop_end() {
+    // a sine loops cleanly when the crossfade starts on a whole period
+    let period = 100;
+    let frames: Vec<Frame> = sine(10_050, 44_000)
+        .into_iter()
+        .map(|s| Frame { left: s, right: s })
+        .collect();
+    let end = find_loop_end(&frames, 500, 1000);
+    let offset = (end - 500) % period;
+    assert!(offset <= 1 || offset >= period - 1, "end {end}");
+}
+
+#[test]
+fn test_write_wav() {
+    let frames = [Frame {
+        left: 1.0,
+        right: -2.0,
+    }; 10];
+    let mut wav = vec![];
+    write_wav(&mut wav, &frames, 48_000).unwrap();
+    assert_eq!(wav.len(), 44 + 10 * 4);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..12], b"WAVE");
+    // samples are clamped
+    assert_eq!(&wav[44..48], [0xff, 0x7f, 0x01, 0x80]);
+}
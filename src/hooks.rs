@@ -1,4 +1,4 @@
-use crate::BOT;
+use crate::{bot::Button, BOT};
 use geometrydash::{get_base, patch_mem, AddressUtils, GameManager, PlayLayer, PlayerObject, Ptr};
 use retour::static_detour;
 use std::ffi::c_void;
@@ -67,7 +67,7 @@ macro_rules! call_hook {
 
 unsafe extern "fastcall" fn push_button(player: PlayerObject, _edx: Ptr, button: i32) -> bool {
     let res = call_hook!(PushButton(player, 0, button), FnPushButton);
-    unsafe { BOT.on_action(true, BOT.is_player2_obj(player)) };
+    unsafe { BOT.on_action(true, BOT.is_player2_obj(player), Button::from_id(button)) };
     res
 }
 
@@ -75,7 +75,7 @@ make_retour_fn!(push_button, push_button_retour(player: PlayerObject, _edx: Ptr,
 
 unsafe extern "fastcall" fn release_button(player: PlayerObject, _edx: Ptr, button: i32) -> bool {
     let res = call_hook!(ReleaseButton(player, 0, button), FnReleaseButton);
-    unsafe { BOT.on_action(false, BOT.is_player2_obj(player)) };
+    unsafe { BOT.on_action(false, BOT.is_player2_obj(player), Button::from_id(button)) };
     res
 }
 
@@ -102,7 +102,7 @@ unsafe extern "fastcall" fn push_button2(
     unsafe { BOT.playlayer = playlayer };
 
     if unsafe { BOT.conf.use_alternate_hook } {
-        unsafe { BOT.on_action(true, !is_player1(playlayer, button)) };
+        unsafe { BOT.on_action(true, !is_player1(playlayer, button), Button::from_id(param)) };
     }
     res
 }
@@ -126,7 +126,13 @@ unsafe extern "fastcall" fn release_button2(
     unsafe { BOT.playlayer = playlayer };
 
     if unsafe { BOT.conf.use_alternate_hook } {
-        unsafe { BOT.on_action(false, !is_player1(playlayer, button)) };
+        unsafe {
+            BOT.on_action(
+                false,
+                !is_player1(playlayer, button),
+                Button::from_id(param),
+            )
+        };
     }
     res
 }
@@ -0,0 +1,347 @@
+use crate::bot::{click_files_in, is_noise_file, Button, ClickType, ClickpackFs};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Patterns for the folder and file names of a clickpack. Patterns are globs
+/// where `*` matches anything and `?` matches a single character. Matching
+/// ignores case, spaces, `_` and `-`, so `player1` also matches `Player_1`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct FolderRules {
+    pub player1: Vec<String>,
+    pub player2: Vec<String>,
+    /// Category folders, the first category with a matching pattern is used.
+    pub categories: Vec<(ClickType, Vec<String>)>,
+    /// Names of files in clickpacks without category folders, without the
+    /// extension. The first category with a matching pattern is used.
+    #[serde(default = "default_file_patterns")]
+    pub files: Vec<(ClickType, Vec<String>)>,
+}
+
+fn patterns(patterns: &[&str]) -> Vec<String> {
+    patterns.iter().map(|p| p.to_string()).collect()
+}
+
+fn default_file_patterns() -> Vec<(ClickType, Vec<String>)> {
+    // releases first, `hardrelease` is a release and not a hard click
+    vec![
+        (
+            ClickType::HardRelease,
+            patterns(&["*hard*release*", "*release*hard*"]),
+        ),
+        (
+            ClickType::SoftRelease,
+            patterns(&["*soft*release*", "*release*soft*"]),
+        ),
+        (
+            ClickType::MicroRelease,
+            patterns(&["*micro*release*", "*release*micro*"]),
+        ),
+        (ClickType::Release, patterns(&["*release*"])),
+        (ClickType::HardClick, patterns(&["*hard*"])),
+        (ClickType::SoftClick, patterns(&["*soft*"])),
+        (ClickType::MicroClick, patterns(&["*micro*"])),
+        (ClickType::Click, patterns(&["*click*", "*press*", "*tap*"])),
+    ]
+}
+
+impl Default for FolderRules {
+    fn default() -> Self {
+        Self {
+            player1: patterns(&["player1", "sounds1", "p1", "1"]),
+            player2: patterns(&["player2", "sounds2", "p2", "2"]),
+            categories: vec![
+                (
+                    ClickType::HardClick,
+                    patterns(&["hardclick*", "click*hard"]),
+                ),
+                (
+                    ClickType::HardRelease,
+                    patterns(&["hardrelease*", "release*hard"]),
+                ),
+                (
+                    ClickType::SoftClick,
+                    patterns(&["softclick*", "click*soft"]),
+                ),
+                (
+                    ClickType::SoftRelease,
+                    patterns(&["softrelease*", "release*soft"]),
+                ),
+                (
+                    ClickType::MicroClick,
+                    patterns(&["microclick*", "click*micro"]),
+                ),
+                (
+                    ClickType::MicroRelease,
+                    patterns(&["microrelease*", "release*micro"]),
+                ),
+                (
+                    ClickType::Click,
+                    patterns(&["click", "clicks", "regularclick*", "normalclick*"]),
+                ),
+                (
+                    ClickType::Release,
+                    patterns(&["release", "releases", "regularrelease*", "normalrelease*"]),
+                ),
+            ],
+            files: default_file_patterns(),
+        }
+    }
+}
+
+/// Lowercase and without the characters that are ignored when matching.
+fn normalize(s: &str) -> Vec<char> {
+    s.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether a folder name matches a pattern, see [FolderRules].
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (normalize(pattern), normalize(name));
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and the name position it's matched up to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            // let the last `*` match one more character
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl FolderRules {
+    #[inline]
+    fn any_matches(patterns: &[String], name: &str) -> bool {
+        patterns.iter().any(|pattern| glob_matches(pattern, name))
+    }
+
+    /// Player of a folder in the clickpack root, `Some(true)` for player 2.
+    pub fn player_of(&self, name: &str) -> Option<bool> {
+        if Self::any_matches(&self.player1, name) {
+            Some(false)
+        } else if Self::any_matches(&self.player2, name) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    pub fn category_of(&self, name: &str) -> Option<ClickType> {
+        self.categories
+            .iter()
+            .find(|(typ, patterns)| *typ != ClickType::None && Self::any_matches(patterns, name))
+            .map(|(typ, _)| *typ)
+    }
+
+    /// Category of a file by its name, for clickpacks without category folders.
+    pub fn category_of_file(&self, path: &Path) -> Option<ClickType> {
+        let stem = path.file_stem()?.to_string_lossy();
+        self.files
+            .iter()
+            .find(|(typ, patterns)| *typ != ClickType::None && Self::any_matches(patterns, &stem))
+            .map(|(typ, _)| *typ)
+    }
+
+    /// Whether a folder is a player or category folder.
+    pub fn is_known(&self, name: &str) -> bool {
+        self.player_of(name).is_some() || self.category_of(name).is_some()
+    }
+}
+
+/// Button of a button bank folder, see [Button::dirname].
+pub fn button_of(name: &str) -> Option<Button> {
+    Button::ALL
+        .into_iter()
+        .find(|button| glob_matches(button.dirname(), name))
+}
+
+/// How the category of a [FolderMapping] was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchedBy {
+    /// The name of the folder.
+    Folder,
+    /// The names of the files, see [FolderRules::category_of_file].
+    FileName,
+    /// Nothing, the files are loaded as clicks.
+    Nothing,
+    /// Loudness and transient shape, see [crate::autosort].
+    Loudness,
+}
+
+/// Files of a folder that are loaded into the same category.
+#[derive(Clone, Debug)]
+pub struct FolderMapping {
+    pub path: PathBuf,
+    pub player2: bool,
+    /// Button bank the folder is in, [None] for the normal categories.
+    pub button: Option<Button>,
+    pub typ: ClickType,
+    pub matched_by: MatchedBy,
+    pub files: Vec<PathBuf>,
+}
+
+#[inline]
+fn dirname(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Player folders of a clickpack, along with whether they're for player 2.
+/// The root comes first and is used for player 1 too.
+pub fn player_dirs(fs: &ClickpackFs, root: &Path, rules: &FolderRules) -> Vec<(PathBuf, bool)> {
+    let mut dirs = vec![(root.to_path_buf(), false)];
+    for dir in fs.dirs_in(root) {
+        if let Some(player2) = rules.player_of(&dirname(&dir)) {
+            dirs.push((dir, player2));
+        }
+    }
+    dirs
+}
+
+/// Find the files that sounds are loaded from. Player folders without category
+/// folders are searched recursively and their files are sorted into categories
+/// by name, files that match nothing are loaded as clicks.
+pub fn map_folders(fs: &ClickpackFs, root: &Path, rules: &FolderRules) -> Vec<FolderMapping> {
+    let mut folders = vec![];
+    for (dir, player2) in player_dirs(fs, root, rules) {
+        let mut categories = vec![];
+        let mut banks = vec![];
+        for subdir in fs.dirs_in(&dir) {
+            let name = dirname(&subdir);
+            if let Some(typ) = rules.category_of(&name) {
+                categories.push(FolderMapping {
+                    files: click_files_in(fs, &subdir),
+                    path: subdir,
+                    player2,
+                    button: None,
+                    typ,
+                    matched_by: MatchedBy::Folder,
+                });
+            } else if let Some(button) = button_of(&name) {
+                banks.extend(fs.dirs_in(&subdir).into_iter().filter_map(|path| {
+                    let typ = rules.category_of(&dirname(&path))?;
+                    Some(FolderMapping {
+                        files: click_files_in(fs, &path),
+                        path,
+                        player2,
+                        button: Some(button),
+                        typ,
+                        matched_by: MatchedBy::Folder,
+                    })
+                }));
+            }
+        }
+
+        if categories.iter().all(|folder| folder.files.is_empty()) {
+            // player folders inside of the root are loaded on their own
+            let is_root = dir == root;
+            let skip = |subdir: &Path| {
+                let name = dirname(subdir);
+                button_of(&name).is_some() || (is_root && rules.player_of(&name).is_some())
+            };
+            categories = classify_files(fs, &dir, player2, rules, &skip);
+            if !categories.is_empty() {
+                log::warn!("no category folders in {dir:?}, sorting files by name");
+            }
+        }
+        folders.extend(categories);
+        folders.extend(banks);
+    }
+    folders
+}
+
+/// Sort the files in `dir` and all of its subfolders into categories by name.
+fn classify_files(
+    fs: &ClickpackFs,
+    dir: &Path,
+    player2: bool,
+    rules: &FolderRules,
+    skip: &dyn Fn(&Path) -> bool,
+) -> Vec<FolderMapping> {
+    let mut folders: Vec<FolderMapping> = vec![];
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for file in click_files_in(fs, &dir) {
+            if is_noise_file(&file) {
+                continue;
+            }
+            let (typ, matched_by) = match rules.category_of_file(&file) {
+                Some(typ) => (typ, MatchedBy::FileName),
+                None => (ClickType::Click, MatchedBy::Nothing),
+            };
+            let folder = folders
+                .iter_mut()
+                .find(|f| f.path == dir && f.typ == typ && f.matched_by == matched_by);
+            match folder {
+                Some(folder) => folder.files.push(file),
+                None => folders.push(FolderMapping {
+                    path: dir.clone(),
+                    player2,
+                    button: None,
+                    typ,
+                    matched_by,
+                    files: vec![file],
+                }),
+            }
+        }
+        // reversed, so folders are visited in order
+        stack.extend(fs.dirs_in(&dir).into_iter().rev().filter(|d| !skip(d)));
+    }
+    folders
+}
+
+#[test]
+fn test_folder_rules() {
+    assert!(glob_matches("player1", "Player_1"));
+    assert!(glob_matches("click*hard", "clicks_hard"));
+    assert!(glob_matches("a*b*c", "aXbYbc"));
+    assert!(glob_matches("p?", "P2"));
+    assert!(!glob_matches("click", "clicks"));
+    assert!(!glob_matches("p?", "p"));
+
+    let rules = FolderRules::default();
+    assert_eq!(rules.player_of("Player 2"), Some(true));
+    assert_eq!(rules.player_of("sounds1"), Some(false));
+    assert_eq!(rules.player_of("clicks"), None);
+    for (name, typ) in [
+        ("hard_clicks", ClickType::HardClick),
+        ("clicks_hard", ClickType::HardClick),
+        ("HardReleases", ClickType::HardRelease),
+        ("Clicks", ClickType::Click),
+        ("releases", ClickType::Release),
+        ("soft-clicks", ClickType::SoftClick),
+        ("microreleases", ClickType::MicroRelease),
+    ] {
+        assert_eq!(rules.category_of(name), Some(typ), "{name}");
+    }
+    assert_eq!(rules.category_of("noise"), None);
+
+    for (name, typ) in [
+        ("hardclick_03.wav", Some(ClickType::HardClick)),
+        ("release (2).ogg", Some(ClickType::Release)),
+        ("micro-1.wav", Some(ClickType::MicroClick)),
+        ("Soft Release 4.wav", Some(ClickType::SoftRelease)),
+        ("click@0.5.wav", Some(ClickType::Click)),
+        ("1.wav", None),
+    ] {
+        assert_eq!(rules.category_of_file(Path::new(name)), typ, "{name}");
+    }
+}
@@ -1,8 +1,19 @@
 #![feature(concat_idents)]
 
+mod autosort;
 mod bot;
+mod builder;
+mod cache;
+mod dsp;
 mod hooks;
+mod layout;
+mod lint;
+mod loudness;
+mod noise;
+mod selection;
+mod simulator;
 mod utils;
+mod watcher;
 
 use bot::BOT;
 use egui_opengl_internal::OpenGLApp;
@@ -190,7 +201,15 @@ unsafe extern "system" fn zcblive_main(_hmod: *mut c_void) -> u32 {
 #[no_mangle]
 #[inline(never)]
 unsafe extern "system" fn zcblive_action_callback(push: bool, player2: bool) {
-    BOT.on_action(push, player2)
+    BOT.on_action(push, player2, bot::Button::Jump)
+}
+
+/// Same as `zcblive_action_callback`, with the button id the game passes to
+/// `pushButton`/`releaseButton` (1 = jump, 2 = left, 3 = right).
+#[no_mangle]
+#[inline(never)]
+unsafe extern "system" fn zcblive_button_action_callback(push: bool, player2: bool, button: i32) {
+    BOT.on_action(push, player2, bot::Button::from_id(button))
 }
 
 #[no_mangle]
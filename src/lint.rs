@@ -0,0 +1,357 @@
+use crate::{
+    bot::{
+        find_clickpack_noise, is_audio_file, read_players, Button, ClickType, ClickpackFs,
+        SoundLoader, Sounds, MANIFEST_FILENAME,
+    },
+    dsp,
+    layout::{self, FolderMapping, FolderRules, MatchedBy},
+};
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Clicks longer than this are probably not clicks.
+const LONG_CLICK_SECS: f32 = 1.0;
+/// Sounds with a peak below this level are considered silent.
+const SILENT_DB: f32 = -60.0;
+/// Number of consecutive full scale samples that count as clipping.
+const CLIP_RUN: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub enum Issue {
+    /// No sounds could be loaded at all.
+    NoSounds,
+    /// A category has no sounds, `fallback` is the category that plays instead.
+    MissingCategory {
+        player2: bool,
+        typ: ClickType,
+        fallback: Option<ClickType>,
+    },
+    /// A category is missing from the folder of a button. `fallback` is the
+    /// category that plays instead, from the same button folder or without one.
+    MissingBankCategory {
+        player2: bool,
+        button: Button,
+        typ: ClickType,
+        fallback: (ClickType, Option<Button>),
+    },
+    /// Clicks have different sample rates, along with the number of files per rate.
+    MixedSampleRates(BTreeMap<u32, usize>),
+    Clipped {
+        path: PathBuf,
+        samples: usize,
+    },
+    Silent {
+        path: PathBuf,
+        peak: f32,
+    },
+    LongClick {
+        path: PathBuf,
+        seconds: f32,
+    },
+    Undecodable {
+        path: PathBuf,
+        error: String,
+    },
+    /// A folder that no sounds are loaded from.
+    IgnoredFolder(PathBuf),
+    /// A file in a clickpack without category folders that couldn't be sorted
+    /// into a category by its name, so it's loaded as a click.
+    Unclassified(PathBuf),
+    /// A file next to the sounds that is skipped because it isn't audio, like
+    /// a readme or a cover image.
+    NotAudio(PathBuf),
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::NoSounds | Self::Undecodable { .. } => Severity::Error,
+            Self::MissingCategory { fallback: None, .. } => Severity::Error,
+            Self::MissingCategory { .. }
+            | Self::MissingBankCategory { .. }
+            | Self::IgnoredFolder(_)
+            | Self::Unclassified(_)
+            | Self::NotAudio(_) => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::NoSounds => "No sounds found".to_string(),
+            Self::MissingCategory {
+                player2,
+                typ,
+                fallback,
+            } => {
+                let player = if *player2 { 2 } else { 1 };
+                match fallback {
+                    Some(fallback) => format!(
+                        "Player {player}: no {}, {} will be used instead",
+                        typ.dirname(),
+                        fallback.dirname()
+                    ),
+                    None => format!("Player {player}: no {}", typ.dirname()),
+                }
+            }
+            Self::MissingBankCategory {
+                player2,
+                button,
+                typ,
+                fallback: (fallback, fallback_button),
+            } => {
+                let player = if *player2 { 2 } else { 1 };
+                let fallback = match fallback_button {
+                    Some(b) => format!("{}/{}", b.dirname(), fallback.dirname()),
+                    None => fallback.dirname().to_string(),
+                };
+                format!(
+                    "Player {player}: no {}/{}, {fallback} will be used instead",
+                    button.dirname(),
+                    typ.dirname()
+                )
+            }
+            Self::MixedSampleRates(rates) => {
+                let rates: Vec<String> = rates
+                    .iter()
+                    .map(|(rate, count)| format!("{rate} Hz ({count} files)"))
+                    .collect();
+                format!("Mixed sample rates: {}", rates.join(", "))
+            }
+            Self::Clipped { path, samples } => {
+                format!("{}: clipped ({samples} samples)", path.display())
+            }
+            Self::Silent { path, peak } => {
+                format!("{}: silent (peak {peak:.1} dBFS)", path.display())
+            }
+            Self::LongClick { path, seconds } => {
+                format!("{}: {seconds:.2}s long, is it a click?", path.display())
+            }
+            Self::Undecodable { path, error } => {
+                format!("{}: failed to decode: {error}", path.display())
+            }
+            Self::IgnoredFolder(path) => format!("{}: folder is ignored", path.display()),
+            Self::Unclassified(path) => {
+                format!("{}: unknown category, loaded as a click", path.display())
+            }
+            Self::NotAudio(path) => format!("{}: not an audio file, skipped", path.display()),
+        }
+    }
+}
+
+/// Result of [analyze]. Paths are relative to the clickpack root.
+#[derive(Clone, Debug, Default)]
+pub struct ClickpackReport {
+    /// Number of sounds of each player, without button folders.
+    pub num_sounds: (usize, usize),
+    /// Number of sounds in the button folders of each player.
+    pub num_bank_sounds: (usize, usize),
+    /// Noise file that will be used, if any.
+    pub noise: Option<PathBuf>,
+    /// Issues sorted by severity, most severe first.
+    pub issues: Vec<Issue>,
+}
+
+impl ClickpackReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == severity)
+            .count()
+    }
+}
+
+fn relative(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+/// Files in the folders that sounds are loaded from that aren't audio files.
+fn non_audio_files(fs: &ClickpackFs, folders: &[FolderMapping]) -> Vec<PathBuf> {
+    let mut dirs: Vec<&Path> = folders.iter().map(|folder| folder.path.as_path()).collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs.into_iter()
+        .flat_map(|dir| fs.files_in(dir))
+        .filter(|path| {
+            !is_audio_file(path) && path.file_name().is_none_or(|f| f != MANIFEST_FILENAME)
+        })
+        .collect()
+}
+
+/// Folders that are neither player, button nor category folders.
+fn ignored_folders(
+    fs: &ClickpackFs,
+    root: &Path,
+    rules: &FolderRules,
+    folders: &[FolderMapping],
+) -> Vec<PathBuf> {
+    let players = layout::player_dirs(fs, root, rules);
+    let is_known = |dir: &Path| {
+        players.iter().any(|(player, _)| player == dir)
+            || folders.iter().any(|folder| folder.path.starts_with(dir))
+    };
+
+    let mut ignored = vec![];
+    for (player, _) in &players {
+        for dir in fs.dirs_in(player) {
+            if is_known(&dir) {
+                continue;
+            }
+            let is_button_dir = dir
+                .file_name()
+                .and_then(|name| layout::button_of(&name.to_string_lossy()))
+                .is_some();
+            if is_button_dir {
+                ignored.extend(fs.dirs_in(&dir).into_iter().filter(|dir| !is_known(dir)));
+            } else {
+                ignored.push(dir);
+            }
+        }
+    }
+    ignored
+}
+
+fn check_categories(sounds: &Sounds, player2: bool, issues: &mut Vec<Issue>) {
+    let preferred = |typ: ClickType| {
+        typ.preferred()
+            .into_iter()
+            .take_while(|&t| t != ClickType::None)
+    };
+    for (typ, _) in sounds.categories() {
+        if sounds.num_sounds_in(typ, None) > 0 {
+            continue;
+        }
+        let fallback = preferred(typ).find(|&t| sounds.num_sounds_in(t, None) > 0);
+        issues.push(Issue::MissingCategory {
+            player2,
+            typ,
+            fallback,
+        });
+    }
+
+    // button folders fall back category by category, see [Sounds::random_sound]
+    for button in Button::ALL {
+        let has_bank = sounds
+            .categories()
+            .iter()
+            .any(|(typ, _)| sounds.num_sounds_in(*typ, Some(button)) > 0);
+        if !has_bank {
+            continue;
+        }
+        for (typ, _) in sounds.categories() {
+            if sounds.num_sounds_in(typ, Some(button)) > 0 {
+                continue;
+            }
+            let Some(fallback) = preferred(typ)
+                .flat_map(|t| [(t, Some(button)), (t, None)])
+                .find(|&(t, bank)| sounds.num_sounds_in(t, bank) > 0)
+            else {
+                continue;
+            };
+            issues.push(Issue::MissingBankCategory {
+                player2,
+                button,
+                typ,
+                fallback,
+            });
+        }
+    }
+}
+
+/// Load a clickpack folder or `.zip` archive and check it for common problems.
+/// Files are analyzed as they are on disk, without load-time processing.
+pub fn analyze(path: &Path, rules: &FolderRules) -> Result<ClickpackReport> {
+    let (fs, root) = ClickpackFs::open(path, rules)?;
+    // no fmod system, we only need the decoded frames
+    let loader = SoundLoader::new(std::ptr::null_mut());
+    let folders = layout::map_folders(&fs, &root, rules);
+    let (p1, p2) = read_players(&fs, &folders, &loader);
+
+    let mut report = ClickpackReport {
+        num_sounds: (p1.num_sounds(), p2.num_sounds()),
+        num_bank_sounds: (p1.num_bank_sounds(), p2.num_bank_sounds()),
+        noise: find_clickpack_noise(&fs, &root, rules).map(|noise| relative(&root, &noise)),
+        issues: vec![],
+    };
+    let issues = &mut report.issues;
+
+    if !p1.has_sounds() && !p2.has_sounds() {
+        issues.push(Issue::NoSounds);
+    } else {
+        check_categories(&p1, false, issues);
+        if p2.has_sounds() {
+            check_categories(&p2, true, issues);
+        }
+    }
+
+    let mut rates = BTreeMap::new();
+    for sounds in [&p1, &p2] {
+        for (_, clicks) in sounds.categories() {
+            for click in clicks {
+                let path = relative(&root, &click.path);
+                let sample_rate = click.sample_rate();
+                *rates.entry(sample_rate).or_insert(0) += 1;
+
+                let peak = dsp::peak_db(&click.frames);
+                if peak < SILENT_DB {
+                    issues.push(Issue::Silent {
+                        path: path.clone(),
+                        peak,
+                    });
+                }
+                let clipped = dsp::clipped_samples(&click.frames, CLIP_RUN);
+                if clipped > 0 {
+                    issues.push(Issue::Clipped {
+                        path: path.clone(),
+                        samples: clipped,
+                    });
+                }
+                let seconds = click.frames.len() as f32 / sample_rate.max(1) as f32;
+                if seconds > LONG_CLICK_SECS {
+                    issues.push(Issue::LongClick { path, seconds });
+                }
+            }
+        }
+        for (path, error) in &sounds.failed {
+            issues.push(Issue::Undecodable {
+                path: relative(&root, path),
+                error: error.clone(),
+            });
+        }
+    }
+    if rates.len() > 1 {
+        issues.push(Issue::MixedSampleRates(rates));
+    }
+
+    issues.extend(
+        folders
+            .iter()
+            .filter(|folder| folder.matched_by == MatchedBy::Nothing)
+            .flat_map(|folder| &folder.files)
+            .map(|path| Issue::Unclassified(relative(&root, path))),
+    );
+    issues.extend(
+        non_audio_files(&fs, &folders)
+            .into_iter()
+            .map(|path| Issue::NotAudio(relative(&root, &path))),
+    );
+    issues.extend(
+        ignored_folders(&fs, &root, rules, &folders)
+            .into_iter()
+            .map(|dir| Issue::IgnoredFolder(relative(&root, &dir))),
+    );
+
+    // stable sort keeps the order of issues with the same severity
+    issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity()));
+    Ok(report)
+}
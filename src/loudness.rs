@@ -0,0 +1,288 @@
+use crate::{
+    bot::{ClickpackFs, Sounds},
+    dsp::{self, Loudness},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum NormalizationMethod {
+    /// Loudest peak reaches the target.
+    #[default]
+    Peak,
+    /// Average RMS level reaches the target.
+    Rms,
+    /// Average K-weighted loudness (LUFS) reaches the target.
+    Lufs,
+}
+
+impl NormalizationMethod {
+    pub const ALL: [Self; 3] = [Self::Peak, Self::Rms, Self::Lufs];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Peak => "Peak",
+            Self::Rms => "RMS",
+            Self::Lufs => "LUFS",
+        }
+    }
+
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::Lufs => "LUFS",
+            _ => "dBFS",
+        }
+    }
+
+    pub const fn default_target(self) -> f32 {
+        match self {
+            Self::Peak => -1.0,
+            Self::Rms => -20.0,
+            Self::Lufs => -18.0,
+        }
+    }
+
+    #[inline]
+    fn level(self, loudness: &Loudness) -> f32 {
+        match self {
+            Self::Peak => loudness.peak,
+            Self::Rms => loudness.rms,
+            Self::Lufs => loudness.lufs,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum NormalizationScope {
+    /// Every category of every player gets its own gain.
+    #[default]
+    Category,
+    /// One gain for the whole clickpack, keeps the balance between categories.
+    Pack,
+}
+
+impl NormalizationScope {
+    pub const ALL: [Self; 2] = [Self::Category, Self::Pack];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Category => "Per category",
+            Self::Pack => "Per clickpack",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NormalizationSettings {
+    pub enabled: bool,
+    pub method: NormalizationMethod,
+    pub scope: NormalizationScope,
+    /// Target level in dBFS (LUFS for [NormalizationMethod::Lufs]).
+    pub target: f32,
+    /// Maximum gain that can be applied, in dB.
+    pub max_gain: f32,
+}
+
+impl Default for NormalizationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            method: NormalizationMethod::default(),
+            scope: NormalizationScope::default(),
+            target: NormalizationMethod::default().default_target(),
+            max_gain: 24.0,
+        }
+    }
+}
+
+/// Measured loudness of clickpack files, saved to `.zcb/cache/loudness.json`
+/// so that reloading a clickpack doesn't measure every file again.
+#[derive(Serialize, Deserialize, Default)]
+pub struct LoudnessCache {
+    entries: HashMap<String, Loudness>,
+    #[serde(skip)]
+    dirty: bool,
+    /// Keys that were looked up since the cache was loaded.
+    #[serde(skip)]
+    used: HashSet<String>,
+}
+
+impl LoudnessCache {
+    const PATH: &'static str = ".zcb/cache/loudness.json";
+
+    pub fn load() -> Self {
+        let Ok(f) = std::fs::File::open(Self::PATH) else {
+            return Self::default();
+        };
+        serde_json::from_reader(std::io::BufReader::new(f))
+            .map_err(|e| log::error!("failed to read loudness cache: {e}"))
+            .unwrap_or_default()
+    }
+
+    /// Save the cache. Entries that weren't looked up since it was loaded are
+    /// dropped first, they belong to files that changed or were deleted, to
+    /// old processing settings or to clickpacks that weren't loaded.
+    pub fn save(&mut self) {
+        let len = self.entries.len();
+        self.entries.retain(|key, _| self.used.contains(key));
+        self.dirty |= self.entries.len() != len;
+        if !self.dirty {
+            return;
+        }
+        let path = Path::new(Self::PATH);
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent)
+                .map_err(|e| log::error!("failed to create {parent:?}: {e}"));
+        }
+        if let Ok(f) = std::fs::File::create(path) {
+            let _ = serde_json::to_writer(f, self)
+                .map_err(|e| log::error!("failed to write loudness cache: {e}"));
+        }
+        self.dirty = false;
+    }
+
+    /// Get the loudness of a sound, measuring it if it's not cached. `key`
+    /// identifies the file, [None] disables caching.
+    fn get(&mut self, key: Option<String>, measure: impl FnOnce() -> Loudness) -> Loudness {
+        let Some(key) = key else {
+            return measure();
+        };
+        self.used.insert(key.clone());
+        *self.entries.entry(key).or_insert_with(|| {
+            self.dirty = true;
+            measure()
+        })
+    }
+}
+
+/// Average level of a group of sounds. Peaks use the loudest sound, the other
+/// methods average the power of all sounds.
+fn group_level(method: NormalizationMethod, levels: &[f32]) -> Option<f32> {
+    let levels: Vec<f32> = levels
+        .iter()
+        .copied()
+        .filter(|&l| l > Loudness::SILENCE)
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    Some(match method {
+        NormalizationMethod::Peak => levels.iter().copied().fold(f32::MIN, f32::max),
+        _ => {
+            let power: f32 = levels.iter().map(|l| 10f32.powf(l / 10.0)).sum();
+            10.0 * (power / levels.len() as f32).log10()
+        }
+    })
+}
+
+/// Multiply the gain of every sound so the clickpack reaches the target level.
+/// `salt` is added to cache keys, it should change whenever the decoded audio
+/// can change (e.g. with load-time processing settings).
+pub fn normalize(
+    settings: &NormalizationSettings,
+    players: [&mut Sounds; 2],
+    fs: &ClickpackFs,
+    cache: &mut LoudnessCache,
+    salt: &str,
+) {
+    let method = settings.method;
+
+    // measure every category of every player
+    let mut groups: Vec<Vec<f32>> = vec![];
+    for sounds in &players {
+        for (_, sounds) in sounds.categories() {
+            let levels = sounds
+                .iter()
+                .map(|sound| {
+                    let key = fs.file_key(&sound.path).map(|key| format!("{key}|{salt}"));
+                    let loudness = cache.get(key, || {
+                        Loudness::measure(&sound.frames, sound.sample_rate())
+                    });
+                    method.level(&loudness)
+                })
+                .collect();
+            groups.push(levels);
+        }
+    }
+    cache.save();
+
+    let gain_for = |level: Option<f32>| {
+        level.map_or(1.0, |level| {
+            dsp::db_to_linear((settings.target - level).min(settings.max_gain))
+        })
+    };
+    let pack_gain = gain_for(group_level(method, &groups.concat()));
+
+    let mut groups = groups.iter();
+    for sounds in players {
+        for (typ, sounds) in sounds.categories_mut() {
+            let levels = groups.next().unwrap();
+            let gain = match settings.scope {
+                NormalizationScope::Category => gain_for(group_level(method, levels)),
+                NormalizationScope::Pack => pack_gain,
+            };
+            if !sounds.is_empty() {
+                log::debug!("normalization gain for {typ:?}: {gain:.3}");
+            }
+            for sound in sounds {
+                sound.gain *= gain;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_normalize() {
+    use crate::bot::{SoundLoader, SoundWrapper};
+    use kittyaudio::{Frame, Sound};
+
+    // a quiet 1 kHz sine, within the maximum gain of every target
+    let sample_rate = 48_000;
+    let frames: Vec<Frame> = (0..sample_rate)
+        .map(|i| {
+            let x = 0.1 * (i as f32 * 1000.0 * std::f32::consts::TAU / sample_rate as f32).sin();
+            Frame { left: x, right: x }
+        })
+        .collect();
+    let loader = SoundLoader::new(std::ptr::null_mut());
+    for method in NormalizationMethod::ALL {
+        let mut players = (Sounds::default(), Sounds::default());
+        players.0.clicks.push(SoundWrapper::from_decoded(
+            &loader,
+            Sound::from_frames(sample_rate, &frames),
+            Path::new("click.wav"),
+        ));
+        let settings = NormalizationSettings {
+            enabled: true,
+            method,
+            target: method.default_target(),
+            ..Default::default()
+        };
+        normalize(
+            &settings,
+            [&mut players.0, &mut players.1],
+            &ClickpackFs::Dir,
+            &mut LoudnessCache::default(),
+            "",
+        );
+
+        let click = &players.0.clicks[0];
+        let gained: Vec<Frame> = click
+            .frames
+            .iter()
+            .map(|f| Frame {
+                left: f.left * click.gain,
+                right: f.right * click.gain,
+            })
+            .collect();
+        let level = method.level(&Loudness::measure(&gained, sample_rate));
+        assert!(
+            (level - settings.target).abs() < 0.1,
+            "{method:?}: {level} instead of {}",
+            settings.target
+        );
+    }
+}
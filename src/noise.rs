@@ -0,0 +1,352 @@
+use crate::dsp;
+use kittyaudio::{Frame, Sound};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Folder with noise files that can be layered on top of any clickpack.
+pub const NOISE_LIBRARY_DIR: &str = ".zcb/noise";
+
+/// Noise file from the noise library that plays along with the clickpack noise.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NoiseLayer {
+    /// Path of the file, relative to [NOISE_LIBRARY_DIR].
+    pub file: PathBuf,
+    pub enabled: bool,
+    pub volume: f32,
+    pub speed: f64,
+}
+
+impl NoiseLayer {
+    pub fn new(file: PathBuf) -> Self {
+        Self {
+            file,
+            enabled: true,
+            volume: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Audio files in the noise library, relative to [NOISE_LIBRARY_DIR].
+pub fn noise_library() -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(NOISE_LIBRARY_DIR)?;
+    let mut files = vec![];
+    for entry in Path::new(NOISE_LIBRARY_DIR).read_dir()? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && crate::bot::is_audio_file(&entry.path()) {
+            files.push(PathBuf::from(entry.file_name()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NoiseLoopSettings {
+    /// Crossfade the end of the noise into its start, so it loops without a click.
+    pub crossfade: bool,
+    /// Length of the crossfade in milliseconds.
+    pub crossfade_ms: f32,
+    /// Move the loop point to where the end matches the start best.
+    pub auto_loop_point: bool,
+}
+
+impl Default for NoiseLoopSettings {
+    fn default() -> Self {
+        Self {
+            crossfade: false,
+            crossfade_ms: 100.0,
+            auto_loop_point: false,
+        }
+    }
+}
+
+impl NoiseLoopSettings {
+    /// How far back from the end the loop point is searched, in seconds.
+    const SEARCH_SECS: f32 = 1.0;
+
+    /// Make a noise sound loop seamlessly. The whole result is looped by both
+    /// backends, so nothing else has to know about the crossfade.
+    pub fn make_loop(&self, sound: Sound) -> Sound {
+        if !self.crossfade {
+            return sound;
+        }
+        let sample_rate = sound.sample_rate();
+        let len = dsp::ms_to_frames(self.crossfade_ms, sample_rate);
+        if len == 0 {
+            return sound;
+        }
+
+        let mut frames = &sound.frames[..];
+        if self.auto_loop_point {
+            // don't cut away more than a quarter of the noise
+            let search =
+                dsp::ms_to_frames(Self::SEARCH_SECS * 1000.0, sample_rate).min(frames.len() / 4);
+            let end = dsp::find_loop_end(frames, len, search);
+            log::debug!("noise loop point at {end}/{} frames", frames.len());
+            frames = &frames[..end];
+        }
+        Sound::from_frames(sample_rate, &dsp::crossfade_loop(frames, len))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NoiseModulationMode {
+    /// Noise gets quieter while clicking.
+    #[default]
+    Duck,
+    /// Noise gets louder while clicking.
+    Swell,
+}
+
+impl NoiseModulationMode {
+    pub const ALL: [Self; 2] = [Self::Duck, Self::Swell];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Duck => "Duck",
+            Self::Swell => "Swell",
+        }
+    }
+}
+
+/// Changes the noise volume with how much the player is clicking.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NoiseModulationSettings {
+    pub enabled: bool,
+    pub mode: NoiseModulationMode,
+    /// How fast the noise reacts to clicking, in milliseconds.
+    pub attack: f32,
+    /// How fast the noise goes back after clicking stops, in milliseconds.
+    pub release: f32,
+    /// Volume difference between idle and clicking, in decibels.
+    pub depth: f32,
+}
+
+impl Default for NoiseModulationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: NoiseModulationMode::default(),
+            attack: 50.0,
+            release: 400.0,
+            depth: 6.0,
+        }
+    }
+}
+
+impl NoiseModulationSettings {
+    /// Volume multiplier of the noise for an envelope level between 0 and 1.
+    /// Swelling is quieter when idle, so the noise never gets louder than its
+    /// volume.
+    pub fn gain(&self, level: f32) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        let amount = match self.mode {
+            NoiseModulationMode::Duck => level,
+            NoiseModulationMode::Swell => 1.0 - level,
+        };
+        dsp::db_to_linear(-self.depth * amount)
+    }
+}
+
+/// Envelope follower of clicking activity. Every action holds the input high
+/// for a moment, the level follows it with the attack and release times.
+#[derive(Default)]
+pub struct ActivityEnvelope {
+    level: f32,
+    last_action: Option<Instant>,
+    last_update: Option<Instant>,
+}
+
+impl ActivityEnvelope {
+    /// So a single click rises the level depending on the attack time.
+    const HOLD: Duration = Duration::from_millis(100);
+
+    #[inline]
+    pub fn trigger(&mut self, now: Instant) {
+        self.last_action = Some(now);
+    }
+
+    /// Advance the envelope to `now`, returns the new level between 0 and 1.
+    pub fn update(&mut self, settings: &NoiseModulationSettings, now: Instant) -> f32 {
+        let dt = self
+            .last_update
+            .map_or(0.0, |t| now.saturating_duration_since(t).as_secs_f32());
+        self.last_update = Some(now);
+
+        let active = self
+            .last_action
+            .is_some_and(|t| now.saturating_duration_since(t) < Self::HOLD);
+        let (target, time) = if active {
+            (1.0, settings.attack)
+        } else {
+            (0.0, settings.release)
+        };
+        let coef = if time <= 0.0 {
+            1.0
+        } else {
+            1.0 - (-dt * 1000.0 / time).exp()
+        };
+        self.level += (target - self.level) * coef;
+        self.level
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NoiseColor {
+    White,
+    #[default]
+    Pink,
+    Brown,
+}
+
+impl NoiseColor {
+    pub const ALL: [Self; 3] = [Self::White, Self::Pink, Self::Brown];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::White => "White",
+            Self::Pink => "Pink",
+            Self::Brown => "Brown",
+        }
+    }
+
+    /// One channel of noise, not normalized.
+    fn generate(self, rng: &mut impl Rng, len: usize) -> Vec<f32> {
+        let mut white = move || rng.gen_range(-1.0f32..1.0);
+        match self {
+            Self::White => (0..len).map(|_| white()).collect(),
+            // Paul Kellet's refined pink noise filter
+            Self::Pink => {
+                let mut b = [0.0f32; 7];
+                (0..len)
+                    .map(|_| {
+                        let w = white();
+                        b[0] = 0.99886 * b[0] + w * 0.0555179;
+                        b[1] = 0.99332 * b[1] + w * 0.0750759;
+                        b[2] = 0.96900 * b[2] + w * 0.153852;
+                        b[3] = 0.86650 * b[3] + w * 0.3104856;
+                        b[4] = 0.55000 * b[4] + w * 0.5329522;
+                        b[5] = -0.7616 * b[5] - w * 0.0168980;
+                        let pink = b.iter().sum::<f32>() + w * 0.5362;
+                        b[6] = w * 0.115926;
+                        pink
+                    })
+                    .collect()
+            }
+            // leaky integrator, so it doesn't drift away
+            Self::Brown => {
+                let mut last = 0.0;
+                (0..len)
+                    .map(|_| {
+                        last = (last + 0.02 * white()) / 1.02;
+                        last
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Built-in noise, for clickpacks without a noise file.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NoiseGeneratorSettings {
+    pub enabled: bool,
+    pub color: NoiseColor,
+    /// RMS level in dBFS.
+    pub level: f32,
+    pub lowpass: bool,
+    /// Low-pass cutoff frequency in Hz.
+    pub cutoff: f32,
+}
+
+impl Default for NoiseGeneratorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: NoiseColor::default(),
+            level: -40.0,
+            lowpass: false,
+            cutoff: 8000.0,
+        }
+    }
+}
+
+impl NoiseGeneratorSettings {
+    /// Length of the generated loop in seconds.
+    const LOOP_SECS: f32 = 10.0;
+    const LOOP_CROSSFADE_MS: f32 = 100.0;
+
+    /// Generate `len` frames of stereo noise.
+    pub fn generate(&self, sample_rate: u32, len: usize) -> Vec<Frame> {
+        let mut rng = rand::thread_rng();
+        let left = self.color.generate(&mut rng, len);
+        let right = self.color.generate(&mut rng, len);
+        let mut frames: Vec<Frame> = left
+            .into_iter()
+            .zip(right)
+            .map(|(left, right)| Frame { left, right })
+            .collect();
+        if self.lowpass {
+            dsp::lowpass(&mut frames, self.cutoff, sample_rate);
+        }
+
+        let gain = dsp::db_to_linear(self.level - dsp::rms_db(&frames));
+        for frame in &mut frames {
+            frame.left *= gain;
+            frame.right *= gain;
+        }
+        frames
+    }
+
+    /// Generate a noise that loops seamlessly.
+    pub fn generate_loop(&self) -> Sound {
+        let sample_rate = dsp::CANONICAL_SAMPLE_RATE;
+        let fade = dsp::ms_to_frames(Self::LOOP_CROSSFADE_MS, sample_rate);
+        let len = dsp::ms_to_frames(Self::LOOP_SECS * 1000.0, sample_rate) + fade;
+        let frames = self.generate(sample_rate, len);
+        Sound::from_frames(sample_rate, &dsp::crossfade_loop(&frames, fade))
+    }
+}
+
+#[test]
+fn test_activity_envelope() {
+    let settings = NoiseModulationSettings::default();
+    let mut env = ActivityEnvelope::default();
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    assert_eq!(env.update(&settings, at(0)), 0.0);
+
+    // spamming raises the level close to 1 after a few attack times
+    for ms in (0..300).step_by(10) {
+        env.trigger(at(ms));
+        env.update(&settings, at(ms));
+    }
+    let clicking = env.update(&settings, at(300));
+    assert!(clicking > 0.9, "{clicking}");
+
+    // and it falls back after the release
+    let idle = env.update(&settings, at(3000));
+    assert!(idle < 0.01, "{idle}");
+    assert!((settings.gain(idle) - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn test_generator_level() {
+    for color in NoiseColor::ALL {
+        let settings = NoiseGeneratorSettings {
+            color,
+            lowpass: color == NoiseColor::White,
+            ..Default::default()
+        };
+        let frames = settings.generate(48_000, 48_000);
+        let rms = dsp::rms_db(&frames);
+        assert!((rms - settings.level).abs() < 0.1, "{color:?}: {rms}");
+    }
+}
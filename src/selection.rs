@@ -0,0 +1,253 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How a sample is picked from a category.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum SelectionMode {
+    /// Weighted random choice, the same sample can play several times in a row.
+    #[default]
+    Random,
+    /// Play every sample once in a random order before any of them repeats.
+    ShuffleBag,
+    /// Play samples in order.
+    RoundRobin,
+    /// Weighted random choice that skips the last N played samples.
+    AvoidRecent,
+}
+
+impl SelectionMode {
+    pub const ALL: [Self; 4] = [
+        Self::Random,
+        Self::ShuffleBag,
+        Self::RoundRobin,
+        Self::AvoidRecent,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Random => "Random",
+            Self::ShuffleBag => "Shuffle bag",
+            Self::RoundRobin => "Round robin",
+            Self::AvoidRecent => "Avoid last N",
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Random => "Pick a random sample every time",
+            Self::ShuffleBag => "Play every sample once in a random order before repeating",
+            Self::RoundRobin => "Play samples one after another",
+            Self::AvoidRecent => "Pick a random sample that wasn't played in the last N clicks",
+        }
+    }
+}
+
+/// How clicks are picked when several clickpacks are layered.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum LayerMode {
+    /// Pick one clickpack for every click, by weight.
+    #[default]
+    Random,
+    /// Play a click from every clickpack at once.
+    Layered,
+}
+
+impl LayerMode {
+    pub const ALL: [Self; 2] = [Self::Random, Self::Layered];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Random => "Random by weight",
+            Self::Layered => "Layered",
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Random => "Every click comes from one clickpack, picked by its weight",
+            Self::Layered => "Every click plays a sound from all clickpacks at once",
+        }
+    }
+}
+
+/// Selection state of a single category.
+///
+/// Samples with a weight of 0 are never picked. In the shuffle bag and round robin
+/// modes every other sample is played equally often, weights only matter in the
+/// random modes.
+#[derive(Default, Clone)]
+pub struct Selector {
+    /// Number of samples the state was built for.
+    len: usize,
+    /// Remaining samples in the shuffle bag, picked from the back.
+    bag: Vec<usize>,
+    /// Next index for round robin.
+    next: usize,
+    /// Recently played samples, newest at the back.
+    recent: VecDeque<usize>,
+}
+
+impl Selector {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Pick the index of the next sample. Returns [None] if there are no samples
+    /// with a non-zero weight.
+    pub fn pick(
+        &mut self,
+        mode: SelectionMode,
+        weights: &[f32],
+        avoid_last: usize,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        if self.len != weights.len() {
+            // the category changed (e.g. a new clickpack was loaded)
+            self.reset();
+            self.len = weights.len();
+        }
+
+        let picked = match mode {
+            SelectionMode::Random => Self::pick_weighted(weights, &[], rng),
+            SelectionMode::ShuffleBag => self.pick_shuffle_bag(weights, rng),
+            SelectionMode::RoundRobin => self.pick_round_robin(weights),
+            SelectionMode::AvoidRecent => {
+                let playable = weights.iter().filter(|&&w| w > 0.0).count();
+                // always leave at least one sample to pick from
+                let avoid = avoid_last.min(playable.saturating_sub(1));
+                let skip = self.recent.iter().rev().take(avoid).copied();
+                Self::pick_weighted(weights, &skip.collect::<Vec<_>>(), rng)
+            }
+        }?;
+
+        self.recent.push_back(picked);
+        while self.recent.len() > avoid_last.max(1) {
+            self.recent.pop_front();
+        }
+        Some(picked)
+    }
+
+    #[inline]
+    fn last(&self) -> Option<usize> {
+        self.recent.back().copied()
+    }
+
+    fn pick_weighted(weights: &[f32], skip: &[usize], rng: &mut impl Rng) -> Option<usize> {
+        let candidates: Vec<usize> = (0..weights.len())
+            .filter(|i| weights[*i] > 0.0 && !skip.contains(i))
+            .collect();
+        candidates
+            .choose_weighted(rng, |&i| weights[i])
+            .ok()
+            .copied()
+    }
+
+    fn pick_shuffle_bag(&mut self, weights: &[f32], rng: &mut impl Rng) -> Option<usize> {
+        // drop samples that were disabled since the bag was filled
+        self.bag
+            .retain(|&i| weights.get(i).is_some_and(|&w| w > 0.0));
+
+        if self.bag.is_empty() {
+            self.bag = (0..weights.len()).filter(|&i| weights[i] > 0.0).collect();
+            self.bag.shuffle(rng);
+
+            // don't start the new bag with the sample that ended the previous one
+            if self.bag.len() > 1 && self.bag.last().copied() == self.last() {
+                let swap_with = rng.gen_range(0..self.bag.len() - 1);
+                let last = self.bag.len() - 1;
+                self.bag.swap(swap_with, last);
+            }
+        }
+        self.bag.pop()
+    }
+
+    fn pick_round_robin(&mut self, weights: &[f32]) -> Option<usize> {
+        for _ in 0..weights.len() {
+            let i = self.next % weights.len();
+            self.next = i + 1;
+            if weights[i] > 0.0 {
+                return Some(i);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+fn pick_many(mode: SelectionMode, weights: &[f32], avoid_last: usize, n: usize) -> Vec<usize> {
+    let mut rng = rand::thread_rng();
+    let mut selector = Selector::default();
+    (0..n)
+        .map(|_| selector.pick(mode, weights, avoid_last, &mut rng).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_no_immediate_repeats() {
+    for mode in [
+        SelectionMode::ShuffleBag,
+        SelectionMode::RoundRobin,
+        SelectionMode::AvoidRecent,
+    ] {
+        for len in 2..6 {
+            let picks = pick_many(mode, &vec![1.0; len], 1, 1000);
+            for pair in picks.windows(2) {
+                assert_ne!(
+                    pair[0], pair[1],
+                    "{mode:?} repeated a sample ({len} samples)"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_shuffle_bag_plays_every_sample() {
+    let picks = pick_many(SelectionMode::ShuffleBag, &[1.0; 5], 1, 500);
+    for bag in picks.chunks(5) {
+        let mut bag = bag.to_vec();
+        bag.sort();
+        assert_eq!(bag, [0, 1, 2, 3, 4]);
+    }
+}
+
+#[test]
+fn test_avoid_last_n() {
+    let n = 3;
+    let picks = pick_many(SelectionMode::AvoidRecent, &[1.0; 5], n, 1000);
+    for window in picks.windows(n + 1) {
+        let (last, recent) = window.split_last().unwrap();
+        assert!(!recent.contains(last), "{last} played again in {recent:?}");
+    }
+}
+
+#[test]
+fn test_round_robin_order() {
+    let picks = pick_many(SelectionMode::RoundRobin, &[1.0, 0.0, 1.0, 1.0], 1, 6);
+    assert_eq!(picks, [0, 2, 3, 0, 2, 3]);
+}
+
+#[test]
+fn test_zero_weights_are_never_picked() {
+    let weights = [0.0, 1.0, 0.0, 2.0];
+    for mode in SelectionMode::ALL {
+        for i in pick_many(mode, &weights, 1, 200) {
+            assert!(weights[i] > 0.0, "{mode:?} picked disabled sample {i}");
+        }
+    }
+    let mut selector = Selector::default();
+    for mode in SelectionMode::ALL {
+        assert_eq!(
+            selector.pick(mode, &[0.0, 0.0], 1, &mut rand::thread_rng()),
+            None
+        );
+    }
+}
+
+#[test]
+fn test_single_sample() {
+    for mode in SelectionMode::ALL {
+        assert_eq!(pick_many(mode, &[1.0], 4, 10), [0; 10]);
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+/// Idle time before the first action, so it plays like the first click of a level.
+const LEAD_IN: f64 = 1.0;
+/// How long a tap is held, in seconds.
+const TAP_SECS: f64 = 0.06;
+/// Time between taps, in seconds.
+const TAP_INTERVAL: f64 = 1.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Pattern {
+    /// Single taps, one per second.
+    #[default]
+    Tap,
+    /// Clicks at a fixed rate.
+    Spam,
+    /// Holds with a pause of the same length in between.
+    Hold,
+    /// Both players spamming, player 2 half a click behind.
+    DualSpam,
+}
+
+impl Pattern {
+    pub const ALL: [Self; 4] = [Self::Tap, Self::Spam, Self::Hold, Self::DualSpam];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Tap => "Taps",
+            Self::Spam => "Spam",
+            Self::Hold => "Hold and release",
+            Self::DualSpam => "Dual player spam",
+        }
+    }
+}
+
+/// A synthetic action, see [crate::bot::Bot::play_action].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SimulatedAction {
+    /// Seconds since the start of the simulation.
+    pub time: f64,
+    pub push: bool,
+    pub player2: bool,
+}
+
+/// Plays click patterns without a level, to hear what the settings sound like.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SimulatorSettings {
+    pub pattern: Pattern,
+    /// Clicks per second when spamming.
+    pub cps: f64,
+    /// Length of a hold in milliseconds.
+    pub hold: f64,
+    /// Number of clicks.
+    pub count: usize,
+}
+
+impl Default for SimulatorSettings {
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::default(),
+            cps: 10.0,
+            hold: 300.0,
+            count: 20,
+        }
+    }
+}
+
+impl SimulatorSettings {
+    /// Actions of the pattern, sorted by time.
+    pub fn actions(&self) -> Vec<SimulatedAction> {
+        let period = 1.0 / self.cps.max(0.1);
+        let hold = self.hold.max(0.0) / 1000.0;
+        // (time between clicks, how long they are held, player 2 offset)
+        let (interval, held, player2_offset) = match self.pattern {
+            Pattern::Tap => (TAP_INTERVAL, TAP_SECS, None),
+            Pattern::Spam => (period, period / 2.0, None),
+            Pattern::Hold => (hold * 2.0, hold, None),
+            Pattern::DualSpam => (period, period / 2.0, Some(period / 2.0)),
+        };
+
+        let mut actions = vec![];
+        for player2 in [false, true] {
+            let offset = match (player2, player2_offset) {
+                (false, _) => 0.0,
+                (true, Some(offset)) => offset,
+                (true, None) => continue,
+            };
+            for i in 0..self.count {
+                let time = LEAD_IN + offset + i as f64 * interval;
+                for (time, push) in [(time, true), (time + held, false)] {
+                    actions.push(SimulatedAction {
+                        time,
+                        push,
+                        player2,
+                    });
+                }
+            }
+        }
+        actions.sort_by(|a, b| a.time.total_cmp(&b.time));
+        actions
+    }
+}
+
+#[test]
+fn test_patterns() {
+    let settings = SimulatorSettings {
+        pattern: Pattern::Spam,
+        cps: 10.0,
+        count: 3,
+        ..Default::default()
+    };
+    let actions = settings.actions();
+    assert_eq!(actions.len(), 6);
+    assert!(actions.iter().step_by(2).all(|a| a.push && !a.player2));
+    assert!((actions[2].time - actions[0].time - 0.1).abs() < 1e-9);
+    assert!((actions[1].time - actions[0].time - 0.05).abs() < 1e-9);
+
+    let dual = SimulatorSettings {
+        pattern: Pattern::DualSpam,
+        ..settings
+    };
+    let actions = dual.actions();
+    assert_eq!(actions.len(), 12);
+    assert_eq!(actions.iter().filter(|a| a.player2).count(), 6);
+    assert!(actions.windows(2).all(|w| w[0].time <= w[1].time));
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+/// Watches the clickpacks folder, and the current clickpack if it's somewhere
+/// else, for changes.
+pub struct ClickpackWatcher {
+    debouncer: Debouncer<RecommendedWatcher>,
+    rx: Receiver<DebounceEventResult>,
+    /// Canonical path of the clickpacks folder.
+    root: PathBuf,
+    /// Clickpack outside of the clickpacks folder that is being watched.
+    extra: Option<PathBuf>,
+    /// Path that was last passed to [ClickpackWatcher::watch_clickpack].
+    clickpack: Option<PathBuf>,
+}
+
+impl ClickpackWatcher {
+    /// Editors usually write files in several steps, wait for them to finish.
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    pub fn new(clickpacks_dir: &Path) -> Result<Self> {
+        let (tx, rx) = channel();
+        let mut debouncer = new_debouncer(Self::DEBOUNCE, tx)?;
+        std::fs::create_dir_all(clickpacks_dir)?;
+        let root = clickpacks_dir.canonicalize()?;
+        debouncer.watcher().watch(&root, RecursiveMode::Recursive)?;
+        log::info!("watching {root:?} for changes");
+        Ok(Self {
+            debouncer,
+            rx,
+            root,
+            extra: None,
+            clickpack: None,
+        })
+    }
+
+    /// Also watch a clickpack that was picked from somewhere else on disk.
+    /// Cheap to call when the clickpack didn't change.
+    pub fn watch_clickpack(&mut self, path: Option<&Path>) {
+        if path == self.clickpack.as_deref() {
+            return;
+        }
+        self.clickpack = path.map(Path::to_path_buf);
+        let path = path.and_then(|p| p.canonicalize().ok());
+        if path == self.extra {
+            return;
+        }
+        if let Some(extra) = self.extra.take() {
+            let _ = self.debouncer.watcher().unwatch(&extra);
+        }
+        let Some(path) = path.filter(|p| !p.starts_with(&self.root)) else {
+            return;
+        };
+        match self
+            .debouncer
+            .watcher()
+            .watch(&path, RecursiveMode::Recursive)
+        {
+            Ok(()) => self.extra = Some(path),
+            Err(e) => log::error!("failed to watch {path:?}: {e}"),
+        }
+    }
+
+    /// Paths that changed since the last call, without blocking.
+    pub fn changes(&self) -> Vec<PathBuf> {
+        let mut changes = vec![];
+        while let Ok(events) = self.rx.try_recv() {
+            match events {
+                Ok(events) => changes.extend(events.into_iter().map(|e| e.path)),
+                Err(e) => log::error!("clickpack watcher error: {e}"),
+            }
+        }
+        changes
+    }
+}